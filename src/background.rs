@@ -0,0 +1,72 @@
+mod environment;
+mod sky;
+
+use environment::Environment;
+use sky::Sky;
+
+use crate::{
+    image::Image,
+    light::{Light, LightSample},
+    ray::Ray,
+    vec3::{Color, Vec3},
+};
+
+// What rays see once they leave the scene without hitting anything
+pub enum Background {
+    // The original blue to white sky, brighter towards the top
+    Gradient,
+    // An equirectangular map of the light arriving from every direction, usually HDR
+    Environment(Environment),
+    // A physically based daylight sky around the sun
+    Sky(Sky),
+}
+
+impl Background {
+    // `rotation` turns the map around the vertical axis, in degrees. Looking down -z sees
+    // the middle of the map when it isn't rotated, the same as the equirectangular camera.
+    pub fn environment(image: Image, rotation: f32, intensity: f32) -> Background {
+        Background::Environment(Environment::new(image, rotation, intensity))
+    }
+
+    // The sun's elevation is in degrees above the horizon and its azimuth in degrees
+    // clockwise from -z, seen from above. Turbidity is how hazy the air is, from about 2
+    // for a very clear day to 10.
+    pub fn sky(elevation: f32, azimuth: f32, turbidity: f32, ground_albedo: Color) -> Background {
+        Background::Sky(Sky::new(elevation, azimuth, turbidity, ground_albedo))
+    }
+
+    // The sun that goes with a sky, to be added to the scene's lights
+    pub fn sun_light(&self) -> Option<Light> {
+        match self {
+            Background::Sky(sky) => Some(sky.sun_light()),
+            _ => None,
+        }
+    }
+
+    pub fn value(&self, ray: &Ray) -> Color {
+        match self {
+            Background::Gradient => {
+                let unit = ray.direction.unit();
+                let t = 0.5 * (1.0 + unit.y);
+                Color::new(1.0, 1.0, 1.0) * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t
+            }
+            Background::Environment(environment) => {
+                // Diffuse surfaces have already sampled it as a light
+                if ray.from_diffuse {
+                    return Color::new(0.0, 0.0, 0.0);
+                }
+
+                environment.value(ray.direction)
+            }
+            Background::Sky(sky) => sky.value(ray),
+        }
+    }
+
+    // Picks a direction to gather light from, for backgrounds that get sampled as lights
+    pub fn sample(&self, _point: Vec3) -> Option<LightSample> {
+        match self {
+            Background::Environment(environment) => environment.sample(),
+            Background::Gradient | Background::Sky(_) => None,
+        }
+    }
+}
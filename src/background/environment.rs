@@ -0,0 +1,104 @@
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+use crate::{
+    degrees_to_radians,
+    distribution::Distribution2D,
+    image::Image,
+    light::LightSample,
+    vec3::{Color, Vec3},
+};
+
+pub struct Environment {
+    image: Image,
+    // Around the vertical axis, in radians
+    rotation: f32,
+    intensity: f32,
+    // Picks pixels by how much light they give, so small bright things like the sun get
+    // found by shadow rays instead of only by the odd lucky bounce
+    distribution: Distribution2D,
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+impl Environment {
+    pub fn new(image: Image, rotation: f32, intensity: f32) -> Self {
+        // Rows near the poles cover less of the sphere, so count for less
+        let func: Vec<f32> = image
+            .pixels
+            .iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let row = i / image.width;
+                let latitude = ((row as f32 + 0.5) / image.height as f32 - 0.5) * PI;
+                pixel.luminance().max(0.0) * latitude.cos()
+            })
+            .collect();
+        let distribution = Distribution2D::new(&func, image.width, image.height);
+
+        Environment {
+            image,
+            rotation: degrees_to_radians(rotation),
+            intensity,
+            distribution,
+        }
+    }
+
+    // Turns a direction by the map's rotation, or back again with a negative angle
+    fn rotate(&self, direction: Vec3, angle: f32) -> Vec3 {
+        let (sin, cos) = angle.sin_cos();
+        Vec3::new(
+            cos * direction.x + sin * direction.z,
+            direction.y,
+            -sin * direction.x + cos * direction.z,
+        )
+    }
+
+    // Pixel lookups are left unfiltered so they match the sampling distribution exactly
+    fn pixel(&self, s: f32, t: f32) -> Color {
+        let x = ((s * self.image.width as f32) as usize).min(self.image.width - 1);
+        let y = (((1.0 - t) * self.image.height as f32) as usize).min(self.image.height - 1);
+        self.image.get(x, y) * self.intensity
+    }
+
+    pub fn value(&self, direction: Vec3) -> Color {
+        let d = self.rotate(direction.unit(), -self.rotation);
+        let longitude = d.x.atan2(-d.z);
+        let latitude = d.y.clamp(-1.0, 1.0).asin();
+
+        self.pixel(longitude / (2.0 * PI) + 0.5, latitude / PI + 0.5)
+    }
+
+    pub fn sample(&self) -> Option<LightSample> {
+        // x runs across the image and y down it from the top
+        let ((x, y), pdf) = self.distribution.sample(rand(), rand());
+        let (s, t) = (x, 1.0 - y);
+
+        let longitude = (s - 0.5) * 2.0 * PI;
+        let latitude = (t - 0.5) * PI;
+        let cos_latitude = latitude.cos();
+
+        if pdf <= 0.0 || cos_latitude <= 0.0 {
+            return None;
+        }
+
+        let direction = Vec3::new(
+            cos_latitude * longitude.sin(),
+            latitude.sin(),
+            -cos_latitude * longitude.cos(),
+        );
+
+        // The map covers 2π by π radians, squeezed together towards the poles
+        let solid_angle_pdf = pdf / (2.0 * PI * PI * cos_latitude);
+
+        Some(LightSample {
+            direction: self.rotate(direction, self.rotation),
+            distance: f32::INFINITY,
+            radiance: self.pixel(s, t) / solid_angle_pdf,
+            blackbody: None,
+        })
+    }
+}
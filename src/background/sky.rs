@@ -0,0 +1,207 @@
+use std::f32::consts::PI;
+
+use crate::{
+    degrees_to_radians,
+    light::Light,
+    ray::Ray,
+    spectrum::xyz_to_srgb,
+    vec3::{Color, Vec3},
+};
+
+// Illuminance of the sun above the atmosphere, in lux
+const SOLAR_ILLUMINANCE: f32 = 128_000.0;
+// In degrees
+const SUN_ANGULAR_DIAMETER: f32 = 0.53;
+// Wavelengths (μm) the sun's transmittance is worked out at for red, green and blue
+const RGB_WAVELENGTHS: [f32; 3] = [0.65, 0.55, 0.45];
+// Steps over the sky's altitude and azimuth when adding up how much light it gives
+const IRRADIANCE_STEPS: usize = 32;
+
+// Preetham, Shirley and Smits' analytic daylight model ("A Practical Analytic Model for
+// Daylight", 1999). Sky radiance is in cd/m² and the sun's irradiance in lux, so daylight
+// scenes want Exposure::Manual or Exposure::Auto.
+pub struct Sky {
+    // Unit vector pointing towards the sun
+    sun_direction: Vec3,
+    // Perez distribution coefficients A to E for Y, x and y
+    coefficients: [[f32; 5]; 3],
+    // Y, x and y straight up, divided by the distribution there so it can be scaled
+    // to any other direction
+    zenith: [f32; 3],
+    sun_irradiance: Color,
+    // Cosine of the angular radius of the sun's disk
+    sun_cos_radius: f32,
+    // Radiance of the ground below the horizon. Preetham's model doesn't cover it,
+    // so it's treated as a diffuse plane with the given albedo lit by the sky and sun.
+    ground: Color,
+}
+
+fn perez(coefficients: &[f32; 5], cos_theta: f32, gamma: f32) -> f32 {
+    let [a, b, c, d, e] = *coefficients;
+    let cos_gamma = gamma.cos();
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+impl Sky {
+    // The sun's elevation is in degrees above the horizon and its azimuth in degrees
+    // clockwise from -z, seen from above. Turbidity goes from about 2 for a very clear
+    // sky to 10 for a hazy one.
+    pub fn new(elevation: f32, azimuth: f32, turbidity: f32, ground_albedo: Color) -> Self {
+        // The model breaks down once the sun reaches the horizon
+        let elevation = degrees_to_radians(elevation).clamp(0.01, PI / 2.0);
+        let azimuth = degrees_to_radians(azimuth);
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+
+        let t = turbidity;
+        let theta_s = PI / 2.0 - elevation;
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        // Zenith luminance (converted from kcd/m²) and chromaticity
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192) * 1000.0;
+
+        let (t2, s, s2, s3) = (t * t, theta_s, theta_s * theta_s, theta_s.powi(3));
+        let x = t2 * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s)
+            + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394)
+            + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886);
+        let y = t2 * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s)
+            + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516)
+            + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688);
+
+        let mut zenith = [luminance, x, y];
+        for (value, coefficients) in zenith.iter_mut().zip(coefficients.iter()) {
+            *value /= perez(coefficients, 1.0, theta_s);
+        }
+
+        let mut sky = Sky {
+            sun_direction,
+            coefficients,
+            zenith,
+            sun_irradiance: Sky::sun_transmittance(theta_s, turbidity) * SOLAR_ILLUMINANCE,
+            sun_cos_radius: degrees_to_radians(SUN_ANGULAR_DIAMETER / 2.0).cos(),
+            ground: Color::new(0.0, 0.0, 0.0),
+        };
+
+        let sun = sky.sun_irradiance * sun_direction.y;
+        sky.ground = ground_albedo * (sky.irradiance() + sun) / PI;
+        sky
+    }
+
+    // How much of the sun's light makes it through the air, losing blue to Rayleigh
+    // scattering and everything to haze, using Kasten's relative air mass
+    fn sun_transmittance(theta_s: f32, turbidity: f32) -> Color {
+        let zenith_degrees = theta_s.to_degrees();
+        let air_mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - zenith_degrees).powf(-1.253));
+        let beta = 0.04608 * turbidity - 0.04586;
+
+        let transmittance = |lambda: f32| {
+            let rayleigh = 0.008735 * lambda.powf(-4.08);
+            let aerosol = beta * lambda.powf(-1.3);
+            (-(rayleigh + aerosol) * air_mass).exp()
+        };
+
+        Color::new(
+            transmittance(RGB_WAVELENGTHS[0]),
+            transmittance(RGB_WAVELENGTHS[1]),
+            transmittance(RGB_WAVELENGTHS[2]),
+        )
+    }
+
+    // Radiance of the sky itself, for directions above the horizon
+    fn sky_radiance(&self, direction: Vec3) -> Color {
+        // The distribution blows up right at the horizon
+        let cos_theta = direction.y.max(0.01);
+        let gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let luminance = self.zenith[0] * perez(&self.coefficients[0], cos_theta, gamma);
+        let x = self.zenith[1] * perez(&self.coefficients[1], cos_theta, gamma);
+        let y = self.zenith[2] * perez(&self.coefficients[2], cos_theta, gamma);
+
+        if y <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        let xyz = Color::new(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+        let rgb = xyz_to_srgb(xyz);
+        Color::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0))
+    }
+
+    // Light from the whole sky falling on flat, level ground
+    fn irradiance(&self) -> Color {
+        let mut total = Color::new(0.0, 0.0, 0.0);
+        let d_theta = PI / 2.0 / IRRADIANCE_STEPS as f32;
+        let d_phi = 2.0 * PI / IRRADIANCE_STEPS as f32;
+
+        for i in 0..IRRADIANCE_STEPS {
+            let theta = (i as f32 + 0.5) * d_theta;
+
+            for j in 0..IRRADIANCE_STEPS {
+                let phi = (j as f32 + 0.5) * d_phi;
+                let direction = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+
+                total +=
+                    self.sky_radiance(direction) * (theta.cos() * theta.sin() * d_theta * d_phi);
+            }
+        }
+
+        total
+    }
+
+    // The sun as a light, so it gets sampled directly
+    pub fn sun_light(&self) -> Light {
+        Light::sun(
+            self.sun_direction,
+            self.sun_irradiance,
+            1.0,
+            SUN_ANGULAR_DIAMETER,
+        )
+    }
+
+    pub fn value(&self, ray: &Ray) -> Color {
+        let direction = ray.direction.unit();
+
+        if direction.y < 0.0 {
+            return self.ground;
+        }
+
+        let mut radiance = self.sky_radiance(direction);
+
+        // The sun's disk, for rays that didn't already sample the sun as a light
+        if !ray.from_diffuse && direction.dot(&self.sun_direction) >= self.sun_cos_radius {
+            let solid_angle = 2.0 * PI * (1.0 - self.sun_cos_radius);
+            radiance += self.sun_irradiance / solid_angle;
+        }
+
+        radiance
+    }
+}
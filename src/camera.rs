@@ -1,5 +1,62 @@
-use crate::{degrees_to_radians, ray::Ray, vec3::Vec3};
+mod aperture;
+mod exposure;
+mod lens_system;
+mod stereo;
 
+pub use aperture::ApertureShape;
+pub use exposure::Exposure;
+pub use lens_system::{LensElement, LensSystem};
+pub use stereo::{StereoCamera, StereoLayout};
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::{
+    degrees_to_radians,
+    hittable::{hit_record::HitRecord, HittableList},
+    rand,
+    ray::Ray,
+    vec3::Vec3,
+};
+
+// Height of a 35mm full frame sensor, in millimetres
+const SENSOR_HEIGHT: f32 = 24.0;
+
+// Autofocus gives up after this many probe rays get blocked by a lens system
+const AUTOFOCUS_ATTEMPTS: usize = 16;
+
+#[derive(Clone, Copy)]
+pub enum FisheyeMapping {
+    // Distance from the image centre is proportional to the angle off the view direction
+    Equidistant,
+    // Keeps the area of things in the image proportional to the solid angle they cover
+    Equisolid,
+}
+
+#[derive(Clone)]
+pub enum Projection {
+    // Pinhole or thin lens camera, where rays spread out from the lens
+    Perspective,
+    // Rays travel parallel to the view direction, so size doesn't change with distance
+    Orthographic,
+    // `fov` (in radians) is measured across the height of the image
+    Fisheye {
+        fov: f32,
+        aspect_ratio: f32,
+        mapping: FisheyeMapping,
+    },
+    // The full 360 by 180 degree sphere of directions, with the view direction in the middle
+    Equirectangular,
+    // Rays from the film traced through every element of a real lens.
+    // The film is measured in millimetres.
+    LensSystem {
+        lens: Arc<LensSystem>,
+        film_width: f32,
+        film_height: f32,
+    },
+}
+
+#[derive(Clone)]
 pub struct Camera {
     origin: Vec3,
     horizontal: Vec3,
@@ -9,6 +66,28 @@ pub struct Camera {
     v: Vec3,
     w: Vec3,
     lens_radius: f32,
+    aperture_shape: ApertureShape,
+    // How far the rear of the lens clips the aperture towards the edges of the frame,
+    // giving cat's eye shaped bokeh. 0 turns it off.
+    cat_eye: f32,
+    focus_dist: f32,
+    projection: Projection,
+    // How far this eye of a stereo pair sits to the right of the pair's centre (negative
+    // for the left eye). Equirectangular cameras use omni-directional stereo, with the eye
+    // perpendicular to each ray.
+    eye_offset: f32,
+    // Distance at which the two eyes' rays cross
+    convergence: f32,
+    // Lens tilt in radians, up/down then left/right, which tilts the plane of focus
+    tilt: (f32, f32),
+    // Rays are spread evenly over the time the shutter is open
+    shutter_open: f32,
+    shutter_close: f32,
+    // Only known for cameras described like a real one, which manual exposure needs
+    f_number: Option<f32>,
+    // In seconds, for manual exposure. Separate from the shutter times, which are in
+    // the time objects move over.
+    shutter_time: Option<f32>,
 }
 
 impl Camera {
@@ -27,13 +106,141 @@ impl Camera {
         let viewport_height = 2.0 * h;
         let viewport_width = viewport_height * aspect_ratio;
 
+        Camera::framed(
+            look_from,
+            look_at,
+            vup,
+            (focus_dist * viewport_width, focus_dist * viewport_height),
+            aperture,
+            focus_dist,
+            Projection::Perspective,
+        )
+    }
+
+    // A camera described like a real one, assuming the world is measured in metres.
+    // The field of view comes from the focal length (in millimetres) on a full frame
+    // sensor, and the lens opening from the focal length divided by the f-number.
+    pub fn physical(
+        aspect_ratio: f32,
+        look_from: Vec3,
+        look_at: Vec3,
+        vup: Vec3,
+        focal_length: f32,
+        f_number: f32,
+        focus_dist: f32,
+    ) -> Self {
+        let vfov = 2.0 * (SENSOR_HEIGHT / (2.0 * focal_length)).atan();
+        let aperture = focal_length / f_number / 1000.0;
+
+        Camera {
+            f_number: Some(f_number),
+            ..Camera::new(
+                aspect_ratio,
+                look_from,
+                look_at,
+                vup,
+                vfov.to_degrees(),
+                aperture,
+                focus_dist,
+            )
+        }
+    }
+
+    // `view_width` is how many world units fit across the image
+    pub fn orthographic(
+        aspect_ratio: f32,
+        look_from: Vec3,
+        look_at: Vec3,
+        vup: Vec3,
+        view_width: f32,
+        aperture: f32,
+        focus_dist: f32,
+    ) -> Self {
+        Camera::framed(
+            look_from,
+            look_at,
+            vup,
+            (view_width, view_width / aspect_ratio),
+            aperture,
+            focus_dist,
+            Projection::Orthographic,
+        )
+    }
+
+    // Panoramic projections are pinhole cameras, so they have no depth of field. A fisheye's
+    // image circle touches the top and bottom of the image, and anything outside it is black.
+    pub fn fisheye(
+        aspect_ratio: f32,
+        look_from: Vec3,
+        look_at: Vec3,
+        vup: Vec3,
+        fov: f32,
+        mapping: FisheyeMapping,
+    ) -> Self {
+        let projection = Projection::Fisheye {
+            fov: degrees_to_radians(fov),
+            aspect_ratio,
+            mapping,
+        };
+
+        Camera::framed(look_from, look_at, vup, (0.0, 0.0), 0.0, 1.0, projection)
+    }
+
+    // Meant for images twice as wide as they are tall
+    pub fn equirectangular(look_from: Vec3, look_at: Vec3, vup: Vec3) -> Self {
+        let projection = Projection::Equirectangular;
+
+        Camera::framed(look_from, look_at, vup, (0.0, 0.0), 0.0, 1.0, projection)
+    }
+
+    // Simulates a real lens in front of a full frame sensor, assuming the world is measured
+    // in metres. The lens gets focused on things `focus_dist` away from the film. Like a real
+    // camera, the film only gets as much light as the lens lets through.
+    pub fn lens_system(
+        aspect_ratio: f32,
+        look_from: Vec3,
+        look_at: Vec3,
+        vup: Vec3,
+        mut lens: LensSystem,
+        focus_dist: f32,
+    ) -> Self {
+        lens.focus(focus_dist * 1000.0);
+
+        let projection = Projection::LensSystem {
+            lens: Arc::new(lens),
+            film_width: SENSOR_HEIGHT * aspect_ratio,
+            film_height: SENSOR_HEIGHT,
+        };
+
+        Camera::framed(
+            look_from,
+            look_at,
+            vup,
+            (0.0, 0.0),
+            0.0,
+            focus_dist,
+            projection,
+        )
+    }
+
+    // Builds the camera basis and the focal plane rays are aimed at. The focal plane is
+    // `size` world units across and sits `focus_dist` in front of the camera.
+    fn framed(
+        look_from: Vec3,
+        look_at: Vec3,
+        vup: Vec3,
+        (width, height): (f32, f32),
+        aperture: f32,
+        focus_dist: f32,
+        projection: Projection,
+    ) -> Self {
         let w = (look_from - look_at).unit();
-        let u = vup.cross(&w);
+        let u = vup.cross(&w).unit();
         let v = w.cross(&u);
 
         let origin = look_from;
-        let horizontal = focus_dist * viewport_width * u;
-        let vertical = focus_dist * viewport_height * v;
+        let horizontal = width * u;
+        let vertical = height * v;
         let lower_left_corner = origin - horizontal / 2.0 - vertical / 2.0 - focus_dist * w;
         let lens_radius = aperture / 2.0;
 
@@ -46,17 +253,345 @@ impl Camera {
             v,
             w,
             lens_radius,
+            aperture_shape: ApertureShape::Circle,
+            cat_eye: 0.0,
+            focus_dist,
+            projection,
+            eye_offset: 0.0,
+            convergence: f32::INFINITY,
+            tilt: (0.0, 0.0),
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            f_number: None,
+            shutter_time: None,
+        }
+    }
+
+    // Slides the lens sideways like a shift lens, as a fraction of the image width and
+    // height. Shifting up keeps verticals straight while looking at tall buildings.
+    pub fn with_shift(mut self, x: f32, y: f32) -> Camera {
+        if let Projection::Perspective | Projection::Orthographic = self.projection {
+            self.lower_left_corner += x * self.horizontal + y * self.vertical;
+        }
+        self
+    }
+
+    // Tilts the lens of a perspective camera (in degrees) so the plane of focus swings
+    // round following the Scheimpflug principle, rather than staying facing the camera.
+    // Positive `pitch` pushes the plane of focus further away towards the top of the
+    // image, positive `yaw` towards the right. Assumes the world is measured in metres.
+    pub fn with_tilt(mut self, pitch: f32, yaw: f32) -> Camera {
+        self.tilt = (degrees_to_radians(pitch), degrees_to_radians(yaw));
+        self
+    }
+
+    // Where the ray aimed at `target` on the untilted focal plane meets the tilted one
+    fn tilted_focus(&self, target: Vec3) -> Vec3 {
+        if self.tilt == (0.0, 0.0) {
+            return target;
+        }
+
+        // Treat the viewport as a full frame sensor to get the focal length of the lens,
+        // then the distance behind the lens the image forms at
+        let focal_length = SENSOR_HEIGHT / 1000.0 * self.focus_dist / self.vertical.length();
+        if focal_length >= self.focus_dist {
+            return target;
+        }
+
+        let image_dist = 1.0 / (1.0 / focal_length - 1.0 / self.focus_dist);
+
+        // Hinge rule: tilting the lens tilts the plane of focus by a lot more
+        let magnification = self.focus_dist / image_dist;
+        let slope_up = magnification * self.tilt.0.tan();
+        let slope_right = magnification * self.tilt.1.tan();
+
+        let center = self.origin - self.focus_dist * self.w;
+        let normal = self.w + slope_up * self.v + slope_right * self.u;
+
+        let direction = target - self.origin;
+        let facing = direction.dot(&normal);
+
+        if facing.abs() < 1e-8 {
+            return target;
+        }
+
+        let t = (center - self.origin).dot(&normal) / facing;
+
+        match t > 0.0 {
+            true => self.origin + direction * t,
+            false => target,
+        }
+    }
+
+    // Moving objects blur over the time between the shutter opening and closing.
+    // Object motion is laid out over times 0 to 1.
+    pub fn with_shutter(mut self, open: f32, close: f32) -> Camera {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    // How long the shutter stays open in seconds, which sets how bright manual exposure
+    // comes out. Doesn't change motion blur, which with_shutter() sets.
+    pub fn with_shutter_time(mut self, seconds: f32) -> Camera {
+        self.shutter_time = Some(seconds);
+        self
+    }
+
+    pub fn f_number(&self) -> Option<f32> {
+        self.f_number
+    }
+
+    pub fn shutter_time(&self) -> Option<f32> {
+        self.shutter_time
+    }
+
+    pub fn with_aperture_shape(mut self, shape: ApertureShape) -> Camera {
+        self.aperture_shape = shape;
+        self
+    }
+
+    // Around 0.5 gives a subtle effect, while 1.0 or more clips bokeh near the corners heavily
+    pub fn with_cat_eye(mut self, strength: f32) -> Camera {
+        self.cat_eye = strength;
+        self
+    }
+
+    // Focuses on whatever is in the middle of the image
+    pub fn with_autofocus(self, world: &HittableList) -> Camera {
+        self.with_autofocus_at(world, 0.5, 0.5)
+    }
+
+    // Focuses on whatever is at (s, t) on the image by casting a ray there.
+    // Keeps the current focus distance if the ray doesn't hit anything.
+    pub fn with_autofocus_at(self, world: &HittableList, s: f32, t: f32) -> Camera {
+        let mut pinhole = self.clone();
+        pinhole.lens_radius = 0.0;
+        pinhole.aperture_shape = ApertureShape::Circle;
+        pinhole.cat_eye = 0.0;
+
+        let probe = (0..AUTOFOCUS_ATTEMPTS).find_map(|_| pinhole.get_ray(s, t).map(|(ray, _)| ray));
+        let mut record = HitRecord::new();
+
+        match probe {
+            Some(ray) if world.hit(&ray, 0.001, f32::INFINITY, &mut record) => {
+                // The focal plane faces the camera, so it's the depth that matters
+                // rather than the distance along the ray
+                let depth = (record.point - self.origin).dot(&-self.w);
+                self.with_focus_dist(depth.max(0.001))
+            }
+            _ => self,
         }
     }
 
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
+    pub fn with_focus_dist(mut self, focus_dist: f32) -> Camera {
+        match &self.projection {
+            Projection::Perspective => {
+                // The focal plane is where rays are aimed, so moving it means scaling it
+                // around the camera to keep the same field of view
+                let scale = focus_dist / self.focus_dist;
+                self.horizontal *= scale;
+                self.vertical *= scale;
+                self.lower_left_corner =
+                    self.origin + (self.lower_left_corner - self.origin) * scale;
+            }
+            Projection::Orthographic => {
+                self.lower_left_corner += (self.focus_dist - focus_dist) * self.w;
+            }
+            Projection::LensSystem {
+                lens,
+                film_width,
+                film_height,
+            } => {
+                let mut lens = LensSystem::clone(lens);
+                lens.focus(focus_dist * 1000.0);
+
+                self.projection = Projection::LensSystem {
+                    lens: Arc::new(lens),
+                    film_width: *film_width,
+                    film_height: *film_height,
+                };
+            }
+            // Pinhole cameras are in focus everywhere
+            Projection::Fisheye { .. } | Projection::Equirectangular => {}
+        }
+
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    // A point on the lens for a ray through (s, t) on the image, or None if the light
+    // gets blocked there
+    fn sample_lens(&self, s: f32, t: f32) -> Option<Vec3> {
+        let point = self.aperture_shape.sample()?;
+
+        if self.cat_eye <= 0.0 {
+            return Some(point);
+        }
+
+        // Only the part of the aperture that also falls inside the rear opening of the lens
+        // lets light through, and that opening slides towards the edge of the frame. Light
+        // blocked by it darkens the edges of the frame as well as clipping the bokeh.
+        let clip = Vec3::new(s - 0.5, t - 0.5, 0.0) * (2.0 * self.cat_eye);
+        ((point - clip).length_squared() <= 1.0).then_some(point)
+    }
+
+    // A copy of this camera moved `offset` to the right for one eye of a stereo pair,
+    // framed so that things `convergence` away line up in both eyes
+    fn eye(&self, offset: f32, convergence: f32) -> Camera {
+        let mut eye = self.clone();
+
+        match self.projection {
+            Projection::Equirectangular => {
+                eye.eye_offset = offset;
+                eye.convergence = convergence;
+            }
+            _ => {
+                // Shift the whole frustum sideways, then skew it back (an off-axis frustum
+                // rather than toeing in) so the views meet at the convergence distance
+                let shift = offset * self.u;
+                eye.origin += shift;
+                eye.lower_left_corner += shift;
+
+                match self.projection {
+                    Projection::Perspective => {
+                        eye.lower_left_corner += -shift * (self.focus_dist / convergence);
+                    }
+                    // There's no flat frustum to skew, so each ray gets toed in instead
+                    Projection::Fisheye { .. } => {
+                        eye.eye_offset = offset;
+                        eye.convergence = convergence;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        eye
+    }
+
+    // The ray and what the light it brings back gets multiplied by. None when the ray gets
+    // blocked by the aperture or inside a lens system, or falls outside a fisheye's image
+    // circle.
+    pub fn get_ray(&self, s: f32, t: f32) -> Option<(Ray, f32)> {
+        let (mut ray, weight) = match &self.projection {
+            Projection::LensSystem {
+                lens,
+                film_width,
+                film_height,
+            } => self.trace_lens(lens, *film_width, *film_height, s, t)?,
+            _ => (self.project(s, t)?, 1.0),
+        };
+
+        ray.time = self.shutter_open + rand() * (self.shutter_close - self.shutter_open);
+        Some((ray, weight))
+    }
+
+    fn trace_lens(
+        &self,
+        lens: &LensSystem,
+        film_width: f32,
+        film_height: f32,
+        s: f32,
+        t: f32,
+    ) -> Option<(Ray, f32)> {
+        // The lens flips the image, so the film is read back to front
+        let film = Vec3::new((0.5 - s) * film_width, (0.5 - t) * film_height, 0.0);
+
+        let (pupil_radius, pupil_z) = lens.rear_pupil();
+        let pupil = Vec3::random_in_unit_disk() * pupil_radius;
+        let target = Vec3::new(pupil.x, pupil.y, pupil_z);
+
+        let (origin, direction) = lens.trace_from_film(film, target - film)?;
+
+        // Irradiance at the film from the pupil's solid angle, falling off with cos⁴ of
+        // the angle the ray leaves the film at
+        let cos_theta = (target - film).unit().z.abs();
+        let weight = cos_theta.powi(4) * PI * pupil_radius * pupil_radius / (pupil_z * pupil_z);
+
+        // Lens space is in millimetres with the scene towards -z
+        let to_world = |p: Vec3| p.x * self.u + p.y * self.v + p.z * self.w;
+        let ray = Ray::new(self.origin + to_world(origin) / 1000.0, to_world(direction));
+        Some((ray, weight))
+    }
+
+    fn project(&self, s: f32, t: f32) -> Option<Ray> {
+        let rd = self.lens_radius * self.sample_lens(s, t)?;
         let offset = self.u * rd.x + self.v * rd.y;
+        let target = self.lower_left_corner + s * self.horizontal + t * self.vertical;
 
-        Ray::new(
-            self.origin + offset,
-            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
-        )
+        match &self.projection {
+            Projection::Perspective => {
+                let focus = self.tilted_focus(target);
+                Some(Ray::new(self.origin + offset, focus - self.origin - offset))
+            }
+            Projection::Orthographic => {
+                // Each pixel gets its own lens, directly behind its point on the focal plane
+                let lens_center = target + self.focus_dist * self.w;
+                Some(Ray::new(
+                    lens_center + offset,
+                    target - lens_center - offset,
+                ))
+            }
+            Projection::Fisheye {
+                fov,
+                aspect_ratio,
+                mapping,
+            } => {
+                // Distance from the centre, where 1 is the top and bottom edges of the image
+                let x = (2.0 * s - 1.0) * aspect_ratio;
+                let y = 2.0 * t - 1.0;
+                let r = (x * x + y * y).sqrt();
+
+                // Outside the image circle, which stays black like on a real circular fisheye
+                if r > 1.0 {
+                    return None;
+                }
+
+                let max_theta = *fov / 2.0;
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * max_theta,
+                    FisheyeMapping::Equisolid => {
+                        2.0 * (r * (max_theta / 2.0).sin()).min(1.0).asin()
+                    }
+                };
+                let phi = y.atan2(x);
+
+                let direction =
+                    theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) - theta.cos() * self.w;
+
+                // Aim at where the ray from the centre of the pair reaches the convergence
+                // distance
+                let target = match self.convergence.is_finite() {
+                    true => direction * self.convergence - self.eye_offset * self.u,
+                    false => direction,
+                };
+                Some(Ray::new(self.origin, target))
+            }
+            Projection::Equirectangular => {
+                let longitude = (s - 0.5) * 2.0 * PI;
+                let latitude = (t - 0.5) * PI;
+
+                let direction = latitude.cos()
+                    * (longitude.sin() * self.u - longitude.cos() * self.w)
+                    + latitude.sin() * self.v;
+
+                if self.eye_offset == 0.0 {
+                    return Some(Ray::new(self.origin, direction));
+                }
+
+                // Each eye sits on a circle around the origin, to the side of the ray
+                let side = longitude.cos() * self.u + longitude.sin() * self.w;
+                let eye = self.origin + self.eye_offset * side;
+                let target = match self.convergence.is_finite() {
+                    true => self.origin + direction * self.convergence - eye,
+                    false => direction,
+                };
+                Some(Ray::new(eye, target))
+            }
+            // Traced separately, since rays can get blocked on the way through the lens
+            Projection::LensSystem { .. } => Some(Ray::new(self.origin, -self.w)),
+        }
     }
 }
 
@@ -0,0 +1,69 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::{image::Image, rand, vec3::Vec3};
+
+// The shape of the lens opening, which is the shape out of focus highlights take on
+#[derive(Clone)]
+pub enum ApertureShape {
+    Circle,
+    // Built with ApertureShape::polygon
+    Polygon(Polygon),
+    // Brighter pixels let more light through. The image is stretched over the square
+    // around the lens, and the lens barrel blocks anything outside the circle.
+    Mask(Arc<Image>),
+}
+
+// A regular polygon like the ones formed by the blades of an iris diaphragm
+#[derive(Clone)]
+pub struct Polygon {
+    blades: u32,
+    // In degrees
+    rotation: f32,
+}
+
+impl ApertureShape {
+    // Fewer than 3 blades can't close around an opening, so they count as 3
+    pub fn polygon(blades: u32, rotation: f32) -> ApertureShape {
+        ApertureShape::Polygon(Polygon {
+            blades: blades.max(3),
+            rotation,
+        })
+    }
+
+    pub fn mask(image: Image) -> ApertureShape {
+        ApertureShape::Mask(Arc::new(image))
+    }
+
+    // A random point on the aperture, within the unit disk. None when the point picked
+    // falls on a part of a mask that blocks the light, which darkens the image by as
+    // much as the mask covers.
+    pub fn sample(&self) -> Option<Vec3> {
+        match self {
+            ApertureShape::Circle => Some(Vec3::random_in_unit_disk()),
+            ApertureShape::Polygon(Polygon { blades, rotation }) => {
+                // Pick one of the equally sized triangles fanning out from the centre,
+                // then a uniform point inside it
+                let segment = 2.0 * PI / *blades as f32;
+                let index = ((rand() * *blades as f32) as u32).min(blades - 1);
+                let angle = rotation.to_radians() + index as f32 * segment;
+
+                let a = Vec3::new(angle.cos(), angle.sin(), 0.0);
+                let b = Vec3::new((angle + segment).cos(), (angle + segment).sin(), 0.0);
+
+                let (mut x, mut y) = (rand(), rand());
+                if x + y > 1.0 {
+                    (x, y) = (1.0 - x, 1.0 - y);
+                }
+
+                Some(a * x + b * y)
+            }
+            ApertureShape::Mask(image) => {
+                let point = Vec3::random_in_unit_disk();
+                let opening = image.sample((point.x + 1.0) / 2.0, (point.y + 1.0) / 2.0);
+
+                (rand() < (opening.x + opening.y + opening.z) / 3.0).then_some(point)
+            }
+        }
+    }
+}
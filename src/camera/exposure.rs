@@ -0,0 +1,93 @@
+use std::io::{Error, ErrorKind};
+
+use super::Camera;
+use crate::vec3::Color;
+
+// Calibration constants from ISO 12232 for saturation based sensitivity
+const SENSOR_CALIBRATION: f32 = 78.0;
+const LENS_TRANSMISSION: f32 = 0.65;
+
+// Luminance auto exposure aims to put the log average of the image at
+const MIDDLE_GREY: f32 = 0.18;
+
+// Turns the radiance the camera collected into pixel values
+#[derive(Clone, Copy)]
+pub enum Exposure {
+    // Pixels are written out exactly as rendered
+    Unscaled,
+    // A real camera's settings, with radiance treated as luminance in cd/m². The f-number
+    // and shutter time come from the camera, which has to be a Camera::physical with a
+    // shutter time set. `compensation` is in stops.
+    Manual { iso: f32, compensation: f32 },
+    // Scales the image so its log average luminance comes out as middle grey,
+    // multiplied by `compensation` stops
+    Auto { compensation: f32 },
+}
+
+impl Exposure {
+    // Fails if the camera is missing settings this exposure needs, or for auto exposure
+    // on a cropped render, so it can be checked before rendering
+    pub fn check(&self, camera: &Camera, cropped: bool) -> Result<(), Error> {
+        match self {
+            Exposure::Manual { .. } if camera.f_number().is_none() => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "manual exposure needs a Camera::physical for its f-number",
+            )),
+            Exposure::Manual { .. } if !camera.shutter_time().is_some_and(|time| time > 0.0) => {
+                Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "manual exposure needs a shutter time from Camera::with_shutter_time",
+                ))
+            }
+            Exposure::Auto { .. } if cropped => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "auto exposure would expose a crop differently to the full image",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // What every pixel of the render gets multiplied by
+    pub fn scale(&self, camera: &Camera, pixels: &[Color]) -> f32 {
+        match *self {
+            Exposure::Unscaled => 1.0,
+            Exposure::Manual { iso, compensation } => {
+                let (Some(f_number), Some(shutter_time)) =
+                    (camera.f_number(), camera.shutter_time())
+                else {
+                    return 1.0;
+                };
+
+                // The luminance that just saturates the sensor maps to 1.0
+                let max_luminance =
+                    SENSOR_CALIBRATION / (iso * LENS_TRANSMISSION) * f_number * f_number
+                        / shutter_time;
+                compensation.exp2() / max_luminance
+            }
+            Exposure::Auto { compensation } => {
+                let average = Exposure::log_average_luminance(pixels);
+
+                if average <= 0.0 {
+                    return 1.0;
+                }
+
+                MIDDLE_GREY / average * compensation.exp2()
+            }
+        }
+    }
+
+    pub fn log_average_luminance(pixels: &[Color]) -> f32 {
+        if pixels.is_empty() {
+            return 0.0;
+        }
+
+        // Keeps pure black pixels from dragging the log to negative infinity
+        let delta = 1e-4;
+        let sum: f32 = pixels
+            .iter()
+            .map(|pixel| (delta + pixel.luminance().max(0.0)).ln())
+            .sum();
+
+        (sum / pixels.len() as f32).exp()
+    }
+}
@@ -0,0 +1,271 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+use crate::vec3::Vec3;
+
+// Passes of the focusing search, each one moving the lens closer to sharp focus
+const FOCUS_ITERATIONS: usize = 8;
+
+// One surface of a lens prescription, all measured in millimetres
+#[derive(Clone, Copy)]
+pub struct LensElement {
+    // Radius of curvature, positive when the centre is towards the film.
+    // 0 marks the aperture stop, which is flat.
+    pub radius: f32,
+    // Distance along the axis to the next surface towards the film
+    pub thickness: f32,
+    // Index of refraction of what's between this surface and the next one.
+    // 0 (the aperture stop) and 1 both mean air.
+    pub ior: f32,
+    // Diameter of the surface
+    pub aperture: f32,
+}
+
+// A real lens made of a stack of spherical surfaces, listed from the front of the lens to
+// the back. Lens space has the film at z = 0 and the scene off towards negative z.
+#[derive(Clone)]
+pub struct LensSystem {
+    elements: Vec<LensElement>,
+}
+
+fn medium(ior: f32) -> f32 {
+    match ior == 0.0 {
+        true => 1.0,
+        false => ior,
+    }
+}
+
+// Refracts the unit direction `d` through a surface with normal `n` facing against it.
+// None when the light is totally internally reflected.
+fn refract(d: Vec3, n: Vec3, eta: f32) -> Option<Vec3> {
+    let cos_i = -d.dot(&n);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+
+    if sin2_t >= 1.0 {
+        return None;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(d * eta + n * (eta * cos_i - cos_t))
+}
+
+impl LensSystem {
+    pub fn new(elements: Vec<LensElement>) -> Self {
+        LensSystem { elements }
+    }
+
+    // Reads a prescription with one surface per line, as whitespace separated
+    // radius, thickness, ior and aperture columns. Lines starting with # are ignored.
+    pub fn load(path: &str) -> Result<LensSystem, Error> {
+        let text = fs::read_to_string(path)?;
+        let mut elements = Vec::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let columns: Vec<f32> = line
+                .split_whitespace()
+                .map(|column| column.parse::<f32>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "bad lens prescription"))?;
+
+            if columns.len() != 4 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "lens prescription lines need 4 columns",
+                ));
+            }
+
+            elements.push(LensElement {
+                radius: columns[0],
+                thickness: columns[1],
+                ior: columns[2],
+                aperture: columns[3],
+            });
+        }
+
+        if elements.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "lens has no elements"));
+        }
+
+        Ok(LensSystem { elements })
+    }
+
+    // A 50mm f/2 double Gauss lens (US patent 2,673,491), a classic normal lens design
+    pub fn double_gauss() -> LensSystem {
+        let element = |radius, thickness, ior, aperture| LensElement {
+            radius,
+            thickness,
+            ior,
+            aperture,
+        };
+
+        LensSystem::new(vec![
+            element(29.475, 3.76, 1.67, 25.2),
+            element(84.83, 0.12, 1.0, 25.2),
+            element(19.275, 4.025, 1.67, 23.0),
+            element(40.77, 3.275, 1.699, 23.0),
+            element(12.75, 5.705, 1.0, 18.0),
+            element(0.0, 4.5, 0.0, 17.1),
+            element(-14.495, 1.18, 1.603, 17.0),
+            element(40.77, 6.065, 1.658, 20.0),
+            element(-20.385, 0.19, 1.0, 20.0),
+            element(437.065, 3.22, 1.717, 20.0),
+            element(-39.73, 40.0, 1.0, 20.0),
+        ])
+    }
+
+    fn rear(&self) -> &LensElement {
+        &self.elements[self.elements.len() - 1]
+    }
+
+    // Distance from the rear surface to the film
+    pub fn film_distance(&self) -> f32 {
+        self.rear().thickness
+    }
+
+    // Radius and z of the back of the lens, where rays from the film are aimed
+    pub fn rear_pupil(&self) -> (f32, f32) {
+        (self.rear().aperture / 2.0, -self.film_distance())
+    }
+
+    // Finds where a ray crosses the surface whose vertex is at `z`, and the surface normal
+    // there facing against the ray. None if it misses or falls outside the aperture.
+    fn intersect(
+        element: &LensElement,
+        z: f32,
+        origin: Vec3,
+        direction: Vec3,
+    ) -> Option<(Vec3, Vec3)> {
+        let (t, normal) = if element.radius == 0.0 {
+            if direction.z == 0.0 {
+                return None;
+            }
+            (
+                (z - origin.z) / direction.z,
+                Vec3::new(0.0, 0.0, -direction.z.signum()),
+            )
+        } else {
+            let center = Vec3::new(0.0, 0.0, z + element.radius);
+            let oc = origin - center;
+            let a = direction.length_squared();
+            let half_b = oc.dot(&direction);
+            let c = oc.length_squared() - element.radius * element.radius;
+            let discrim = half_b * half_b - a * c;
+
+            if discrim < 0.0 {
+                return None;
+            }
+
+            let sqrtd = discrim.sqrt();
+            let (t0, t1) = ((-half_b - sqrtd) / a, (-half_b + sqrtd) / a);
+
+            // Which of the two crossings is the actual lens surface depends on which way
+            // the ray is going and which way the surface curves
+            let closer = (direction.z > 0.0) != (element.radius < 0.0);
+            let t = match closer {
+                true => t0.min(t1),
+                false => t0.max(t1),
+            };
+
+            let mut normal = (origin + direction * t - center).unit();
+            if normal.dot(&direction) > 0.0 {
+                normal = -normal;
+            }
+            (t, normal)
+        };
+
+        if t <= 0.0 {
+            return None;
+        }
+
+        let point = origin + direction * t;
+        let half = element.aperture / 2.0;
+
+        if point.x * point.x + point.y * point.y > half * half {
+            return None;
+        }
+
+        Some((point, normal))
+    }
+
+    // Follows a ray leaving the film out through the front of the lens.
+    // None if it gets blocked by the lens barrel or the aperture stop.
+    pub fn trace_from_film(&self, origin: Vec3, direction: Vec3) -> Option<(Vec3, Vec3)> {
+        let (mut origin, mut direction) = (origin, direction.unit());
+        let mut z = 0.0;
+
+        for i in (0..self.elements.len()).rev() {
+            let element = &self.elements[i];
+            z -= element.thickness;
+
+            let (point, normal) = LensSystem::intersect(element, z, origin, direction)?;
+            origin = point;
+
+            if element.radius != 0.0 {
+                let outside = match i {
+                    0 => 1.0,
+                    _ => medium(self.elements[i - 1].ior),
+                };
+                direction = refract(direction, normal, medium(element.ior) / outside)?.unit();
+            }
+        }
+
+        Some((origin, direction))
+    }
+
+    // Follows a ray from the scene in through the front of the lens towards the film
+    pub fn trace_from_scene(&self, origin: Vec3, direction: Vec3) -> Option<(Vec3, Vec3)> {
+        let (mut origin, mut direction) = (origin, direction.unit());
+        let mut z: f32 = -self.elements.iter().map(|e| e.thickness).sum::<f32>();
+
+        for (i, element) in self.elements.iter().enumerate() {
+            let (point, normal) = LensSystem::intersect(element, z, origin, direction)?;
+            origin = point;
+
+            if element.radius != 0.0 {
+                let outside = match i {
+                    0 => 1.0,
+                    _ => medium(self.elements[i - 1].ior),
+                };
+                direction = refract(direction, normal, outside / medium(element.ior))?.unit();
+            }
+
+            z += element.thickness;
+        }
+
+        Some((origin, direction))
+    }
+
+    // Moves the lens so things `distance` millimetres in front of the film are sharp.
+    // Moving the lens also changes the field of view slightly, like a real lens.
+    pub fn focus(&mut self, distance: f32) {
+        for _ in 0..FOCUS_ITERATIONS {
+            // A ray close to the axis from the point that should be in focus
+            let front_z: f32 = -self.elements.iter().map(|e| e.thickness).sum::<f32>();
+            let height = self.elements[0].aperture * 0.05;
+            let origin = Vec3::new(0.0, 0.0, -distance);
+            let direction = Vec3::new(height, 0.0, front_z + distance);
+
+            let (exit, exit_direction) = match self.trace_from_scene(origin, direction) {
+                Some(ray) => ray,
+                None => return,
+            };
+
+            if exit_direction.x.abs() < 1e-8 {
+                return;
+            }
+
+            // Where the ray comes back to the axis is where the image forms
+            let t = -exit.x / exit_direction.x;
+            let image_z = exit.z + exit_direction.z * t;
+
+            let last = self.elements.len() - 1;
+            self.elements[last].thickness = (self.elements[last].thickness + image_z).max(0.0);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use super::Camera;
+use crate::ray::Ray;
+
+#[derive(Clone, Copy)]
+pub enum StereoLayout {
+    // Left eye on the left half of the image, right eye on the right half
+    SideBySide,
+    // Left eye on the top half of the image, right eye on the bottom half
+    OverUnder,
+}
+
+impl StereoLayout {
+    // Size of the image holding both eyes, when each eye is `width` by `height`
+    pub fn image_size(&self, width: i32, height: i32) -> (i32, i32) {
+        match self {
+            StereoLayout::SideBySide => (width * 2, height),
+            StereoLayout::OverUnder => (width, height * 2),
+        }
+    }
+}
+
+// Renders both eyes of a stereo pair into a single image. The camera it's built from
+// should be framed for one eye, so a side by side image is twice as wide as that
+// camera's aspect ratio and an over under image is twice as tall.
+pub struct StereoCamera {
+    left: Camera,
+    right: Camera,
+    layout: StereoLayout,
+}
+
+impl StereoCamera {
+    // `interpupillary_distance` is the distance between the eyes in world units, and
+    // things `convergence` away from the camera appear at the depth of the screen.
+    // Orthographic and lens system cameras keep their eyes parallel whatever it's set to.
+    pub fn new(
+        camera: Camera,
+        interpupillary_distance: f32,
+        convergence: f32,
+        layout: StereoLayout,
+    ) -> Self {
+        let half = interpupillary_distance / 2.0;
+
+        StereoCamera {
+            left: camera.eye(-half, convergence),
+            right: camera.eye(half, convergence),
+            layout,
+        }
+    }
+
+    pub fn get_ray(&self, s: f32, t: f32) -> Option<(Ray, f32)> {
+        match self.layout {
+            StereoLayout::SideBySide => match s < 0.5 {
+                true => self.left.get_ray(s * 2.0, t),
+                false => self.right.get_ray(s * 2.0 - 1.0, t),
+            },
+            StereoLayout::OverUnder => match t >= 0.5 {
+                true => self.left.get_ray(s, t * 2.0 - 1.0),
+                false => self.right.get_ray(s, t * 2.0),
+            },
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// Piecewise constant distribution over [0, 1), with each bin picked in proportion
+// to its value
+pub struct Distribution1D {
+    func: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<f32>) -> Self {
+        let n = func.len() as f32;
+        let mut cdf = Vec::with_capacity(func.len() + 1);
+        cdf.push(0.0);
+
+        for value in func.iter() {
+            cdf.push(cdf[cdf.len() - 1] + value.max(0.0) / n);
+        }
+
+        let integral = cdf[cdf.len() - 1];
+
+        // With nothing to go on, every bin is as likely as any other
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = match integral > 0.0 {
+                true => *c / integral,
+                false => i as f32 / n,
+            };
+        }
+
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    // Turns a uniform random number into a point in [0, 1), its probability density
+    // and the bin it landed in
+    pub fn sample(&self, u: f32) -> (f32, f32, usize) {
+        let bin = (self.cdf.partition_point(|c| *c <= u) - 1).min(self.func.len() - 1);
+
+        let width = self.cdf[bin + 1] - self.cdf[bin];
+        let offset = match width > 0.0 {
+            true => ((u - self.cdf[bin]) / width).clamp(0.0, 1.0),
+            false => 0.0,
+        };
+
+        let pdf = match self.integral > 0.0 {
+            true => self.func[bin].max(0.0) / self.integral,
+            false => 1.0,
+        };
+
+        let x = ((bin as f32 + offset) / self.func.len() as f32).min(1.0 - f32::EPSILON);
+        (x, pdf, bin)
+    }
+
+    // Picks a bin, returning it and the probability it had of being picked
+    pub fn sample_discrete(&self, u: f32) -> (usize, f32) {
+        let (_, pdf, bin) = self.sample(u);
+        (bin, pdf / self.func.len() as f32)
+    }
+}
+
+// Piecewise constant distribution over [0, 1)², stored as rows. A row is picked by how
+// much it holds in total, then a point along it.
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f32], width: usize, height: usize) -> Self {
+        let rows: Vec<Distribution1D> = func
+            .chunks_exact(width)
+            .take(height)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral()).collect());
+
+        Distribution2D { rows, marginal }
+    }
+
+    // Returns the point, with y being the row direction, and its probability density
+    pub fn sample(&self, u: f32, v: f32) -> ((f32, f32), f32) {
+        let (y, row_pdf, row) = self.marginal.sample(v);
+        let (x, column_pdf, _) = self.rows[row].sample(u);
+        ((x, y), row_pdf * column_pdf)
+    }
+}
@@ -1,29 +1,151 @@
+pub mod constant_medium;
+pub mod disk;
+pub mod grid_medium;
 pub mod hit_record;
+pub mod quad;
 pub mod sphere;
+pub mod triangle;
 
+use constant_medium::ConstantMedium;
+use disk::Disk;
+use grid_medium::GridMedium;
 use hit_record::HitRecord;
-use sphere::Sphere;
+use quad::Quad;
+use sphere::{Motion, Sphere};
+use triangle::Triangle;
 
-use crate::{material::Material, ray::Ray, vec3::Vec3};
+use crate::{
+    aabb::Aabb,
+    material::Material,
+    ray::{Ray, RayKind},
+    vec3::Vec3,
+};
+
+// Index of an object in the world, in the order it was added
+pub type ObjectId = usize;
+
+// Which kinds of rays can see an object
+#[derive(Clone, Copy)]
+pub struct Visibility {
+    pub camera: bool,
+    // Seen in reflections and refractions, and by light bouncing around the scene
+    pub reflections: bool,
+    pub casts_shadows: bool,
+}
+
+impl Visibility {
+    fn sees(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.camera,
+            RayKind::Indirect => self.reflections,
+            RayKind::Shadow => self.casts_shadows,
+        }
+    }
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Visibility {
+            camera: true,
+            reflections: true,
+            casts_shadows: true,
+        }
+    }
+}
 
 pub enum Hittable {
     Sphere(Sphere),
+    Triangle(Triangle),
+    Quad(Quad),
+    Disk(Disk),
+    ConstantMedium(ConstantMedium),
+    GridMedium(GridMedium),
 }
 
 impl Hittable {
     pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, hit_record: &mut HitRecord) -> bool {
         match self {
-            Hittable::Sphere(object) => object.hit(&ray, t_min, t_max, hit_record),
+            Hittable::Sphere(object) => object.hit(ray, t_min, t_max, hit_record),
+            Hittable::Triangle(object) => object.hit(ray, t_min, t_max, hit_record),
+            Hittable::Quad(object) => object.hit(ray, t_min, t_max, hit_record),
+            Hittable::Disk(object) => object.hit(ray, t_min, t_max, hit_record),
+            Hittable::ConstantMedium(object) => object.hit(ray, t_min, t_max, hit_record),
+            Hittable::GridMedium(object) => object.hit(ray, t_min, t_max, hit_record),
+        }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        match self {
+            Hittable::Sphere(object) => object.bounding_box(),
+            Hittable::Triangle(object) => object.bounding_box(),
+            Hittable::Quad(object) => object.bounding_box(),
+            Hittable::Disk(object) => object.bounding_box(),
+            Hittable::ConstantMedium(object) => object.bounding_box(),
+            Hittable::GridMedium(object) => object.bounding_box(),
+        }
+    }
+
+    // Media let some light through, so shadow rays ask how much rather than hitting them
+    pub fn is_medium(&self) -> bool {
+        matches!(self, Hittable::ConstantMedium(_) | Hittable::GridMedium(_))
+    }
+
+    // Fraction of light getting through the object between t_min and t_max along the ray.
+    // Surfaces are left to hit() to block completely.
+    pub fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        match self {
+            Hittable::ConstantMedium(object) => object.transmittance(ray, t_min, t_max),
+            Hittable::GridMedium(object) => object.transmittance(ray, t_min, t_max),
+            _ => 1.0,
         }
     }
 
     pub fn sphere(center: Vec3, radius: f32, material: Material) -> Hittable {
         Hittable::Sphere(Sphere::new(center, radius, material))
     }
+
+    // Moves in a straight line from `start` to `end` over times 0 to 1
+    pub fn moving_sphere(start: Vec3, end: Vec3, radius: f32, material: Material) -> Hittable {
+        Hittable::Sphere(Sphere::moving(
+            start,
+            Motion::Linear { end },
+            radius,
+            material,
+        ))
+    }
+
+    // Follows a path through (time, center) keyframes, which must be sorted by time
+    pub fn keyframed_sphere(keys: Vec<(f32, Vec3)>, radius: f32, material: Material) -> Hittable {
+        let start = keys.first().map_or(Vec3::new(0.0, 0.0, 0.0), |(_, c)| *c);
+        Hittable::Sphere(Sphere::moving(
+            start,
+            Motion::Keyframed(keys),
+            radius,
+            material,
+        ))
+    }
+
+    pub fn triangle(a: Vec3, b: Vec3, c: Vec3, material: Material) -> Hittable {
+        Hittable::Triangle(Triangle::new(a, b, c, material))
+    }
+
+    pub fn quad(corner: Vec3, u: Vec3, v: Vec3, material: Material) -> Hittable {
+        Hittable::Quad(Quad::new(corner, u, v, material))
+    }
+
+    pub fn disk(center: Vec3, normal: Vec3, radius: f32, material: Material) -> Hittable {
+        Hittable::Disk(Disk::new(center, normal, radius, material))
+    }
+
+    // Fills a closed boundary with a medium, scattering with a phase function material
+    // like Material::isotropic. The boundary's own material is never used.
+    pub fn constant_medium(boundary: Hittable, density: f32, phase: Material) -> Hittable {
+        Hittable::ConstantMedium(ConstantMedium::new(boundary, density, phase))
+    }
 }
 
 pub struct HittableList {
-    objects: Vec<Hittable>,
+    objects: Vec<(Hittable, Visibility)>,
 }
 
 impl HittableList {
@@ -31,21 +153,58 @@ impl HittableList {
         HittableList { objects: vec![] }
     }
 
-    pub fn add(&mut self, object: Hittable) {
-        self.objects.push(object);
+    pub fn add(&mut self, object: Hittable) -> ObjectId {
+        self.add_with_visibility(object, Visibility::default())
+    }
+
+    pub fn add_with_visibility(&mut self, object: Hittable, visibility: Visibility) -> ObjectId {
+        self.objects.push((object, visibility));
+        self.objects.len() - 1
     }
 
     pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, hit_record: &mut HitRecord) -> bool {
         let mut hit = false;
         let mut smallest_t = t_max;
 
-        for object in self.objects.iter() {
-            if object.hit(&ray, t_min, smallest_t, hit_record) {
+        for (id, (object, visibility)) in self.objects.iter().enumerate() {
+            if !visibility.sees(ray.kind) || (ray.kind == RayKind::Shadow && object.is_medium()) {
+                continue;
+            }
+
+            if object.hit(ray, t_min, smallest_t, hit_record) {
                 hit = true;
                 smallest_t = hit_record.time;
+                hit_record.object = Some(id);
             }
         }
 
         hit
     }
+
+    // How much light makes it through every medium that casts shadows between t_min and
+    // t_max, for shadow rays that didn't hit anything solid
+    pub fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.objects
+            .iter()
+            .filter(|(object, visibility)| visibility.casts_shadows && object.is_medium())
+            .map(|(object, _)| object.transmittance(ray, t_min, t_max))
+            .product()
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.objects
+            .iter()
+            .map(|(object, _)| object.bounding_box())
+            .reduce(|a, b| a.surrounding(&b))
+            .unwrap_or(Aabb::new(
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 0.0),
+            ))
+    }
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
+    }
 }
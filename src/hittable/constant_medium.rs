@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use super::{hit_record::HitRecord, Hittable};
+use crate::{aabb::Aabb, material::Material, ray::Ray};
+
+// Smoke or fog of the same density all the way through the inside of a closed boundary.
+// Rays pass some random distance into it before scattering, or go straight through.
+pub struct ConstantMedium {
+    boundary: Box<Hittable>,
+    density: f32,
+    phase: Arc<Material>,
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Hittable, density: f32, phase: Material) -> Self {
+        ConstantMedium {
+            boundary: Box::new(boundary),
+            density,
+            phase: Arc::new(phase),
+        }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    // Where the ray's line goes in and back out of the boundary, clipped to [t_min, t_max].
+    // The boundary can be behind the origin for rays starting inside.
+    fn interval(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        let mut entry = HitRecord::new();
+        if !self
+            .boundary
+            .hit(ray, f32::NEG_INFINITY, f32::INFINITY, &mut entry)
+        {
+            return None;
+        }
+
+        let mut exit = HitRecord::new();
+        if !self
+            .boundary
+            .hit(ray, entry.time + 0.0001, f32::INFINITY, &mut exit)
+        {
+            return None;
+        }
+
+        let t0 = entry.time.max(t_min).max(0.0);
+        let t1 = exit.time.min(t_max);
+
+        match t0 < t1 {
+            true => Some((t0, t1)),
+            false => None,
+        }
+    }
+
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, hit_record: &mut HitRecord) -> bool {
+        let Some((t0, t1)) = self.interval(ray, t_min, t_max) else {
+            return false;
+        };
+
+        // Free flight distance, exponentially distributed
+        let length = ray.direction.length();
+        let distance = -(1.0 - rand()).ln() / self.density;
+
+        if distance > (t1 - t0) * length {
+            return false;
+        }
+
+        hit_record.set_in_medium(ray, t0 + distance / length, self.phase.clone());
+        true
+    }
+
+    pub fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        match self.interval(ray, t_min, t_max) {
+            Some((t0, t1)) => (-self.density * (t1 - t0) * ray.direction.length()).exp(),
+            None => 1.0,
+        }
+    }
+}
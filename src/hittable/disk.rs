@@ -0,0 +1,101 @@
+use crate::aabb::Aabb;
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::rand;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+// A flat circle facing along `normal`
+pub struct Disk {
+    center: Vec3,
+    normal: Vec3,
+    radius: f32,
+    // Directions in the plane of the disk, used for its uv coordinates
+    tangent: Vec3,
+    bitangent: Vec3,
+    material: Arc<Material>,
+}
+
+impl Disk {
+    pub fn new(center: Vec3, normal: Vec3, radius: f32, material: Material) -> Self {
+        let normal = normal.unit();
+        let (tangent, bitangent) = Disk::basis(&normal);
+
+        Disk {
+            center,
+            normal,
+            radius,
+            tangent,
+            bitangent,
+            material: Arc::new(material),
+        }
+    }
+
+    // Any two directions perpendicular to the normal and each other
+    pub fn basis(normal: &Vec3) -> (Vec3, Vec3) {
+        let helper = match normal.x.abs() > 0.9 {
+            true => Vec3::new(0.0, 1.0, 0.0),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let tangent = helper.cross(normal).unit();
+        (tangent, normal.cross(&tangent))
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        // How far the rim reaches along each axis
+        let extent = Vec3::new(
+            (1.0 - self.normal.x * self.normal.x).max(0.0).sqrt(),
+            (1.0 - self.normal.y * self.normal.y).max(0.0).sqrt(),
+            (1.0 - self.normal.z * self.normal.z).max(0.0).sqrt(),
+        ) * self.radius;
+
+        Aabb::new(self.center - extent, self.center + extent).padded(1e-4)
+    }
+
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, hit_record: &mut HitRecord) -> bool {
+        let denominator = self.normal.dot(&ray.direction);
+
+        // Parallel to the plane
+        if denominator.abs() < 1e-8 {
+            return false;
+        }
+
+        let root = self.normal.dot(&(self.center - ray.origin)) / denominator;
+
+        if root < t_min || t_max < root {
+            return false;
+        }
+
+        let point = ray.at(root);
+        let offset = point - self.center;
+
+        if offset.length_squared() > self.radius * self.radius {
+            return false;
+        }
+
+        // Angle around the disk and distance from the middle
+        let x = offset.dot(&self.tangent);
+        let y = offset.dot(&self.bitangent);
+        let u = (y.atan2(x) + PI) / (2.0 * PI);
+        let v = offset.length() / self.radius;
+
+        let lobe_sample = rand();
+        if !self.material.alpha_test(u, v, point, lobe_sample) {
+            return false;
+        }
+
+        hit_record.time = root;
+        hit_record.point = point;
+        hit_record.material = self.material.clone();
+        hit_record.lobe_sample = lobe_sample;
+        hit_record.set_normal(ray, &self.normal);
+        hit_record.u = u;
+        hit_record.v = v;
+        hit_record.tangent = self.tangent;
+        hit_record.bitangent = self.bitangent;
+
+        true
+    }
+}
@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use super::hit_record::HitRecord;
+use crate::{
+    aabb::Aabb,
+    degrees_to_radians,
+    material::Material,
+    ray::Ray,
+    spectrum::Blackbody,
+    vec3::{Color, Vec3},
+    voxel_grid::VoxelGrid,
+};
+
+// Temperatures get rounded to this many kelvin when looking up their color
+const TEMPERATURE_STEP: f32 = 25.0;
+
+// Anything cooler doesn't visibly glow
+const MIN_GLOW_TEMPERATURE: f32 = 500.0;
+
+// Smoke, clouds or fire with a density that varies through a box, read from a voxel grid.
+// The grid fills a box of the given size around `center`, turned about the vertical axis.
+pub struct GridMedium {
+    density: VoxelGrid,
+    // Multiplies the grid's values to get the density per unit length
+    density_scale: f32,
+    // Densest anywhere in the grid, which delta tracking takes steps against
+    majorant: f32,
+    phase: Arc<Material>,
+    emission: Option<(VoxelGrid, Color)>,
+    temperature: Option<Temperature>,
+    center: Vec3,
+    size: Vec3,
+    cos_rotation: f32,
+    sin_rotation: f32,
+}
+
+// Blackbody glow from a grid of temperatures
+struct Temperature {
+    grid: VoxelGrid,
+    // Multiplies the grid's values to get kelvin
+    scale: f32,
+    // Blackbodies every TEMPERATURE_STEP kelvin up to the hottest in the grid, with
+    // how bright each one glows
+    table: Vec<(Blackbody, f32)>,
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+// Turns `v` about the y axis by the angle with the given cosine and sine
+fn rotate_y(v: Vec3, cos: f32, sin: f32) -> Vec3 {
+    Vec3::new(cos * v.x + sin * v.z, v.y, -sin * v.x + cos * v.z)
+}
+
+impl GridMedium {
+    pub fn new(density: VoxelGrid, density_scale: f32, phase: Material) -> Self {
+        GridMedium {
+            majorant: density.max() * density_scale,
+            density,
+            density_scale,
+            phase: Arc::new(phase),
+            emission: None,
+            temperature: None,
+            center: Vec3::new(0.0, 0.0, 0.0),
+            size: Vec3::new(1.0, 1.0, 1.0),
+            cos_rotation: 1.0,
+            sin_rotation: 0.0,
+        }
+    }
+
+    // `rotation` is in degrees
+    pub fn with_transform(self, center: Vec3, size: Vec3, rotation: f32) -> Self {
+        let rotation = degrees_to_radians(rotation);
+
+        GridMedium {
+            center,
+            size,
+            cos_rotation: rotation.cos(),
+            sin_rotation: rotation.sin(),
+            ..self
+        }
+    }
+
+    // Glows with `color` times the grid's value
+    pub fn with_emission(self, grid: VoxelGrid, color: Color) -> Self {
+        GridMedium {
+            emission: Some((grid, color)),
+            ..self
+        }
+    }
+
+    // Glows like a blackbody at the grid's temperature times `scale` kelvin. The hottest
+    // voxel has the given luminance and cooler ones are as much dimmer as Planck's law says.
+    pub fn with_temperature(self, grid: VoxelGrid, scale: f32, luminance: f32) -> Self {
+        let hottest = grid.max() * scale;
+        let steps = (hottest / TEMPERATURE_STEP).ceil() as usize;
+        let brightest = Blackbody::new(hottest.max(MIN_GLOW_TEMPERATURE)).unscaled_luminance();
+
+        let table = (0..=steps)
+            .map(|i| {
+                let blackbody = Blackbody::new((i as f32 * TEMPERATURE_STEP).max(1.0));
+                let brightness = blackbody.unscaled_luminance() / brightest * luminance;
+                (blackbody, brightness)
+            })
+            .collect();
+
+        GridMedium {
+            temperature: Some(Temperature { grid, scale, table }),
+            ..self
+        }
+    }
+
+    // Brings a ray into the grid's unit cube. Directions aren't normalised, so distances
+    // along the ray stay the same.
+    fn to_local(&self, ray: &Ray) -> Ray {
+        let (cos, sin) = (self.cos_rotation, -self.sin_rotation);
+        let origin = rotate_y(ray.origin - self.center, cos, sin) / self.size + 0.5;
+        let direction = rotate_y(ray.direction, cos, sin) / self.size;
+
+        let mut local = Ray::new(origin, direction);
+        local.time = ray.time;
+        local
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        let mut bounds: Option<Aabb> = None;
+
+        for corner in 0..8 {
+            let offset = Vec3::new(
+                if corner & 1 == 0 { -0.5 } else { 0.5 },
+                if corner & 2 == 0 { -0.5 } else { 0.5 },
+                if corner & 4 == 0 { -0.5 } else { 0.5 },
+            ) * self.size;
+            let point = self.center + rotate_y(offset, self.cos_rotation, self.sin_rotation);
+            let around = Aabb::new(point, point);
+
+            bounds = Some(match bounds {
+                Some(bounds) => bounds.surrounding(&around),
+                None => around,
+            });
+        }
+
+        bounds.unwrap().padded(0.0001)
+    }
+
+    // The part of [t_min, t_max] the ray spends inside the grid, in local space
+    fn interval(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(Ray, f32, f32)> {
+        if self.majorant <= 0.0 {
+            return None;
+        }
+
+        let local = self.to_local(ray);
+        let unit = Aabb::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        let (t0, t1) = unit.hit_interval(&local, t_min, t_max)?;
+        Some((local, t0, t1))
+    }
+
+    // Tentative collisions happen as if the whole grid were as dense as its densest voxel
+    fn step(&self, ray: &Ray, t: f32) -> f32 {
+        t - (1.0 - rand()).ln() / (self.majorant * ray.direction.length())
+    }
+
+    // Delta tracking, which turns tentative collisions into real ones in proportion to
+    // how dense the grid really is there
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, hit_record: &mut HitRecord) -> bool {
+        let Some((local, t0, t1)) = self.interval(ray, t_min, t_max) else {
+            return false;
+        };
+
+        let mut t = t0;
+        loop {
+            t = self.step(ray, t);
+            if t >= t1 {
+                return false;
+            }
+
+            let point = local.at(t);
+            let density = self.density.value(point) * self.density_scale;
+
+            if rand() * self.majorant < density {
+                hit_record.set_in_medium(ray, t, self.material_at(point));
+                return true;
+            }
+        }
+    }
+
+    // Ratio tracking, which gives the fraction of light getting through rather than
+    // randomly blocking all or none of it like delta tracking would
+    pub fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let Some((local, t0, t1)) = self.interval(ray, t_min, t_max) else {
+            return 1.0;
+        };
+
+        let mut transmittance = 1.0;
+        let mut t = t0;
+        loop {
+            t = self.step(ray, t);
+            if t >= t1 {
+                return transmittance;
+            }
+
+            let density = self.density.value(local.at(t)) * self.density_scale;
+            transmittance *= 1.0 - density / self.majorant;
+
+            // Russian roulette once hardly any light is left, so thick media end early
+            if transmittance < 0.1 {
+                if rand() < 0.5 {
+                    return 0.0;
+                }
+                transmittance *= 2.0;
+            }
+        }
+    }
+
+    // The phase function, glowing if there's any emission at `point`
+    fn material_at(&self, point: Vec3) -> Arc<Material> {
+        let mut emission = Color::new(0.0, 0.0, 0.0);
+        let mut blackbody = None;
+
+        if let Some((grid, color)) = &self.emission {
+            emission += *color * grid.value(point);
+        }
+
+        if let Some(temperature) = &self.temperature {
+            let kelvin = temperature.grid.value(point) * temperature.scale;
+            let i = (kelvin / TEMPERATURE_STEP).round().max(0.0) as usize;
+
+            if kelvin >= MIN_GLOW_TEMPERATURE && i < temperature.table.len() {
+                let (body, brightness) = temperature.table[i];
+                emission += body.color() * brightness;
+
+                // Mixed with plain emission the spectrum isn't a blackbody's any more
+                if self.emission.is_none() {
+                    blackbody = Some(body);
+                }
+            }
+        }
+
+        match &*self.phase {
+            Material::PhaseFunction(phase) if !emission.near_zero() => Arc::new(
+                Material::PhaseFunction(phase.with_emission(emission, blackbody)),
+            ),
+            _ => self.phase.clone(),
+        }
+    }
+}
@@ -1,14 +1,29 @@
+use super::ObjectId;
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::{Color, Vec3};
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct HitRecord {
     pub point: Vec3,
+    // The shading normal, which normal and bump maps are allowed to tilt
     pub normal: Vec3,
+    // The true normal of the surface, facing against the ray like `normal`
+    pub geometric_normal: Vec3,
+    // Directions on the surface that u and v increase along
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
     pub time: f32,
+    // Surface coordinates used to look up textures
+    pub u: f32,
+    pub v: f32,
     pub front_face: bool,
-    pub material: Rc<Material>,
+    pub material: Arc<Material>,
+    // Random number that picks which lobe of a Mix the hit uses. It's drawn once per hit
+    // so the alpha test and the shading agree on the lobe.
+    pub lobe_sample: f32,
+    // Which object in the world was hit, None for the scene's fog
+    pub object: Option<ObjectId>,
 }
 
 impl HitRecord {
@@ -16,9 +31,16 @@ impl HitRecord {
         HitRecord {
             point: Vec3::new(0.0, 0.0, 0.0),
             normal: Vec3::new(0.0, 0.0, 0.0),
+            geometric_normal: Vec3::new(0.0, 0.0, 0.0),
+            tangent: Vec3::new(0.0, 0.0, 0.0),
+            bitangent: Vec3::new(0.0, 0.0, 0.0),
             time: 0.0,
+            u: 0.0,
+            v: 0.0,
             front_face: true,
-            material: Rc::new(Material::lambertian(Color::new(0.0, 0.0, 0.0))),
+            material: Arc::new(Material::lambertian(Color::new(0.0, 0.0, 0.0))),
+            lobe_sample: 0.0,
+            object: None,
         }
     }
 
@@ -27,6 +49,27 @@ impl HitRecord {
         self.normal = match self.front_face {
             true => *outward_normal,
             false => -*outward_normal,
-        }
+        };
+        self.geometric_normal = self.normal;
+    }
+
+    // A scattering event inside a medium rather than on a surface. There's no surface
+    // to face, so the normals are left at zero.
+    pub fn set_in_medium(&mut self, ray: &Ray, t: f32, phase: Arc<Material>) {
+        self.time = t;
+        self.point = ray.at(t);
+        self.normal = Vec3::new(0.0, 0.0, 0.0);
+        self.geometric_normal = self.normal;
+        self.tangent = self.normal;
+        self.bitangent = self.normal;
+        (self.u, self.v) = (0.0, 0.0);
+        self.front_face = true;
+        self.material = phase;
+    }
+}
+
+impl Default for HitRecord {
+    fn default() -> Self {
+        Self::new()
     }
 }
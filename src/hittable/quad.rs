@@ -0,0 +1,84 @@
+use crate::aabb::Aabb;
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::rand;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+// A parallelogram with one corner at `corner` and sides along `u` and `v`
+pub struct Quad {
+    corner: Vec3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    // Plane constant, so that normal · p = d for every point p on the quad
+    d: f32,
+    // Used to find the quad coordinates of a point on the plane
+    w: Vec3,
+    material: Arc<Material>,
+}
+
+impl Quad {
+    pub fn new(corner: Vec3, u: Vec3, v: Vec3, material: Material) -> Self {
+        let n = u.cross(&v);
+        let normal = n.unit();
+
+        Quad {
+            corner,
+            u,
+            v,
+            normal,
+            d: normal.dot(&corner),
+            w: n / n.length_squared(),
+            material: Arc::new(material),
+        }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.corner, self.corner + self.u + self.v)
+            .surrounding(&Aabb::new(self.corner + self.u, self.corner + self.v))
+            .padded(1e-4)
+    }
+
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, hit_record: &mut HitRecord) -> bool {
+        let denominator = self.normal.dot(&ray.direction);
+
+        // Parallel to the plane
+        if denominator.abs() < 1e-8 {
+            return false;
+        }
+
+        let root = (self.d - self.normal.dot(&ray.origin)) / denominator;
+
+        if root < t_min || t_max < root {
+            return false;
+        }
+
+        let point = ray.at(root);
+        let planar = point - self.corner;
+        let alpha = self.w.dot(&planar.cross(&self.v));
+        let beta = self.w.dot(&self.u.cross(&planar));
+
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return false;
+        }
+
+        let lobe_sample = rand();
+        if !self.material.alpha_test(alpha, beta, point, lobe_sample) {
+            return false;
+        }
+
+        hit_record.time = root;
+        hit_record.point = point;
+        hit_record.material = self.material.clone();
+        hit_record.lobe_sample = lobe_sample;
+        hit_record.set_normal(ray, &self.normal);
+        hit_record.u = alpha;
+        hit_record.v = beta;
+        hit_record.tangent = self.u.unit();
+        hit_record.bitangent = self.v.unit();
+
+        true
+    }
+}
@@ -1,26 +1,83 @@
+use crate::aabb::Aabb;
 use crate::hittable::HitRecord;
 use crate::material::Material;
+use crate::rand;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
-use std::rc::Rc;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+// How the center of a sphere moves while the shutter is open, over times 0 to 1
+pub enum Motion {
+    Static,
+    // Straight line from the sphere's center at time 0 to `end` at time 1
+    Linear { end: Vec3 },
+    // Moves between (time, center) pairs sorted by time, holding still before the
+    // first and after the last
+    Keyframed(Vec<(f32, Vec3)>),
+}
 
 pub struct Sphere {
     center: Vec3,
     radius: f32,
-    material: Material,
+    material: Arc<Material>,
+    motion: Motion,
 }
 
 impl Sphere {
     pub fn new(center: Vec3, radius: f32, material: Material) -> Self {
+        Sphere::moving(center, Motion::Static, radius, material)
+    }
+
+    pub fn moving(center: Vec3, motion: Motion, radius: f32, material: Material) -> Self {
         Sphere {
             center,
             radius,
-            material,
+            material: Arc::new(material),
+            motion,
+        }
+    }
+
+    pub fn center_at(&self, time: f32) -> Vec3 {
+        match &self.motion {
+            Motion::Static => self.center,
+            Motion::Linear { end } => {
+                let time = time.clamp(0.0, 1.0);
+                self.center * (1.0 - time) + *end * time
+            }
+            Motion::Keyframed(keys) => {
+                let next = keys.iter().position(|(key_time, _)| *key_time > time);
+
+                match next {
+                    None => keys.last().map_or(self.center, |(_, center)| *center),
+                    Some(0) => keys[0].1,
+                    Some(i) => {
+                        let (t0, c0) = keys[i - 1];
+                        let (t1, c1) = keys[i];
+                        let s = (time - t0) / (t1 - t0);
+                        c0 * (1.0 - s) + c1 * s
+                    }
+                }
+            }
+        }
+    }
+
+    // Covers everywhere the sphere goes while moving
+    pub fn bounding_box(&self) -> Aabb {
+        let r = self.radius.abs();
+        let around = |center: Vec3| Aabb::new(center - r, center + r);
+
+        match &self.motion {
+            Motion::Static => around(self.center),
+            Motion::Linear { end } => around(self.center).surrounding(&around(*end)),
+            Motion::Keyframed(keys) => keys.iter().fold(around(self.center), |bounds, (_, c)| {
+                bounds.surrounding(&around(*c))
+            }),
         }
     }
 
     pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, hit_record: &mut HitRecord) -> bool {
-        let center = self.center;
+        let center = self.center_at(ray.time);
         let radius = self.radius;
 
         let oc = ray.origin - center;
@@ -35,22 +92,56 @@ impl Sphere {
         }
 
         let sqrtd = discrim.sqrt();
-        let mut root = (-half_b - sqrtd) / a;
 
-        if root < t_min || t_max < root {
-            root = (-half_b + sqrtd) / a;
+        // Try the near side first, then the far side if the near one is out of
+        // range or cut away by the material
+        for root in [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a] {
             if root < t_min || t_max < root {
-                return false;
+                continue;
+            }
+
+            let point = ray.at(root);
+            let normal = (point - center) / radius;
+            let (u, v) = Sphere::uv(&normal);
+
+            let lobe_sample = rand();
+            if !self.material.alpha_test(u, v, point, lobe_sample) {
+                continue;
             }
+
+            hit_record.time = root;
+            hit_record.point = point;
+            hit_record.material = self.material.clone();
+            hit_record.lobe_sample = lobe_sample;
+            hit_record.set_normal(ray, &normal);
+            (hit_record.u, hit_record.v) = (u, v);
+            (hit_record.tangent, hit_record.bitangent) = Sphere::tangents(&normal);
+
+            return true;
         }
 
-        hit_record.time = root;
-        hit_record.point = ray.at(root);
-        hit_record.material = Rc::new(self.material);
+        false
+    }
+
+    // Maps a point on the unit sphere to (u, v), with u going around the y axis
+    // starting from -x and v going from the bottom pole to the top
+    fn uv(point: &Vec3) -> (f32, f32) {
+        let theta = (-point.y).clamp(-1.0, 1.0).acos();
+        let phi = (-point.z).atan2(point.x) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    // Directions u and v increase along at a point on the unit sphere
+    fn tangents(point: &Vec3) -> (Vec3, Vec3) {
+        let mut tangent = Vec3::new(point.z, 0.0, -point.x);
 
-        let normal = (hit_record.point - center) / radius;
-        hit_record.set_normal(&ray, &normal);
+        // u is undefined at the poles, so any direction on the surface will do
+        if tangent.near_zero() {
+            tangent = Vec3::new(1.0, 0.0, 0.0);
+        }
 
-        true
+        let tangent = tangent.unit();
+        (tangent, point.cross(&tangent))
     }
 }
@@ -0,0 +1,116 @@
+use crate::aabb::Aabb;
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::rand;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+pub struct Triangle {
+    vertices: [Vec3; 3],
+    // Texture coordinates of each vertex
+    uvs: [(f32, f32); 3],
+    normal: Vec3,
+    tangent: Vec3,
+    bitangent: Vec3,
+    material: Arc<Material>,
+}
+
+impl Triangle {
+    pub fn new(a: Vec3, b: Vec3, c: Vec3, material: Material) -> Self {
+        Triangle::with_uvs([a, b, c], [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)], material)
+    }
+
+    pub fn with_uvs(vertices: [Vec3; 3], uvs: [(f32, f32); 3], material: Material) -> Self {
+        let edge1 = vertices[1] - vertices[0];
+        let edge2 = vertices[2] - vertices[0];
+        let normal = edge1.cross(&edge2).unit();
+
+        // Solve for the directions u and v increase along from the uv deltas of the edges
+        let (du1, dv1) = (uvs[1].0 - uvs[0].0, uvs[1].1 - uvs[0].1);
+        let (du2, dv2) = (uvs[2].0 - uvs[0].0, uvs[2].1 - uvs[0].1);
+        let determinant = du1 * dv2 - du2 * dv1;
+
+        let (tangent, bitangent) = if determinant.abs() < 1e-8 {
+            // Degenerate uvs, so any frame on the surface will do
+            let tangent = edge1.unit();
+            (tangent, normal.cross(&tangent))
+        } else {
+            let r = 1.0 / determinant;
+            let tangent = (edge1 * dv2 - edge2 * dv1) * r;
+            let bitangent = (edge2 * du1 - edge1 * du2) * r;
+            (tangent.unit(), bitangent.unit())
+        };
+
+        Triangle {
+            vertices,
+            uvs,
+            normal,
+            tangent,
+            bitangent,
+            material: Arc::new(material),
+        }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.vertices[0], self.vertices[1])
+            .surrounding(&Aabb::new(self.vertices[2], self.vertices[2]))
+            .padded(1e-4)
+    }
+
+    // Möller–Trumbore intersection
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, hit_record: &mut HitRecord) -> bool {
+        let edge1 = self.vertices[1] - self.vertices[0];
+        let edge2 = self.vertices[2] - self.vertices[0];
+
+        let p = ray.direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+
+        if determinant.abs() < 1e-8 {
+            return false;
+        }
+
+        let inverse = 1.0 / determinant;
+        let s = ray.origin - self.vertices[0];
+        let b1 = s.dot(&p) * inverse;
+
+        if !(0.0..=1.0).contains(&b1) {
+            return false;
+        }
+
+        let q = s.cross(&edge1);
+        let b2 = ray.direction.dot(&q) * inverse;
+
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return false;
+        }
+
+        let root = edge2.dot(&q) * inverse;
+
+        if root < t_min || t_max < root {
+            return false;
+        }
+
+        let b0 = 1.0 - b1 - b2;
+        let point = ray.at(root);
+        let u = b0 * self.uvs[0].0 + b1 * self.uvs[1].0 + b2 * self.uvs[2].0;
+        let v = b0 * self.uvs[0].1 + b1 * self.uvs[1].1 + b2 * self.uvs[2].1;
+
+        let lobe_sample = rand();
+        if !self.material.alpha_test(u, v, point, lobe_sample) {
+            return false;
+        }
+
+        hit_record.time = root;
+        hit_record.point = point;
+        hit_record.material = self.material.clone();
+        hit_record.lobe_sample = lobe_sample;
+        hit_record.set_normal(ray, &self.normal);
+        hit_record.u = u;
+        hit_record.v = v;
+        hit_record.tangent = self.tangent;
+        hit_record.bitangent = self.bitangent;
+
+        true
+    }
+}
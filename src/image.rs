@@ -0,0 +1,440 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+use crate::vec3::Color;
+
+// A grid of linear colors, stored row by row starting from the top left
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+impl Image {
+    // Reads ASCII (P3) or binary (P6) PPM files, normalised to the 0..1 range
+    pub fn load_ppm(path: &str) -> Result<Image, Error> {
+        let bytes = fs::read(path)?;
+        let mut position = 0;
+        let mut header = Vec::new();
+
+        // Magic number, width, height and max value, skipping whitespace and comments
+        while header.len() < 4 {
+            while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+
+            if position >= bytes.len() {
+                return Err(invalid("PPM header ended early"));
+            }
+
+            if bytes[position] == b'#' {
+                while position < bytes.len() && bytes[position] != b'\n' {
+                    position += 1;
+                }
+                continue;
+            }
+
+            let start = position;
+            while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            header.push(String::from_utf8_lossy(&bytes[start..position]).to_string());
+        }
+
+        let parse = |token: &str| {
+            token
+                .parse::<usize>()
+                .map_err(|_| invalid("bad PPM header"))
+        };
+        let width = parse(&header[1])?;
+        let height = parse(&header[2])?;
+        let max_value = parse(&header[3])? as f32;
+        let count = width * height * 3;
+
+        let values: Vec<f32> = match header[0].as_str() {
+            "P3" => String::from_utf8_lossy(&bytes[position..])
+                .split_ascii_whitespace()
+                .take(count)
+                .map(|token| token.parse::<f32>().map_err(|_| invalid("bad PPM value")))
+                .collect::<Result<_, _>>()?,
+            "P6" => {
+                // Exactly one whitespace byte separates the header from the data
+                let data = bytes.get(position + 1..).unwrap_or_default();
+                if max_value < 256.0 {
+                    data.iter().take(count).map(|b| *b as f32).collect()
+                } else {
+                    data.chunks_exact(2)
+                        .take(count)
+                        .map(|b| u16::from_be_bytes([b[0], b[1]]) as f32)
+                        .collect()
+                }
+            }
+            _ => return Err(invalid("only P3 and P6 PPM files are supported")),
+        };
+
+        if values.len() < count {
+            return Err(invalid("PPM file is missing pixel data"));
+        }
+
+        let pixels = values
+            .chunks_exact(3)
+            .map(|rgb| Color::new(rgb[0], rgb[1], rgb[2]) / max_value)
+            .collect();
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    // Picks the loader from the file extension, falling back to PPM
+    pub fn load(path: &str) -> Result<Image, Error> {
+        let extension = path.rsplit('.').next().unwrap_or_default();
+
+        match extension.to_ascii_lowercase().as_str() {
+            "hdr" => Image::load_hdr(path),
+            "pfm" => Image::load_pfm(path),
+            _ => Image::load_ppm(path),
+        }
+    }
+
+    // Reads Radiance RGBE files, either flat or with the usual per channel run length
+    // encoding. Values are kept as they are, so can go well past 1.
+    pub fn load_hdr(path: &str) -> Result<Image, Error> {
+        Image::parse_hdr(&fs::read(path)?)
+    }
+
+    pub fn parse_hdr(bytes: &[u8]) -> Result<Image, Error> {
+        let mut position = 0;
+        let read_line = |position: &mut usize| {
+            let start = *position;
+            while *position < bytes.len() && bytes[*position] != b'\n' {
+                *position += 1;
+            }
+            let line = String::from_utf8_lossy(&bytes[start..*position]).to_string();
+            *position += 1;
+            line
+        };
+
+        if !read_line(&mut position).starts_with("#?") {
+            return Err(invalid("not a Radiance HDR file"));
+        }
+
+        // Header variables run up to the first blank line
+        loop {
+            if position >= bytes.len() {
+                return Err(invalid("HDR header ended early"));
+            }
+
+            let line = read_line(&mut position);
+
+            if line.is_empty() {
+                break;
+            }
+
+            if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+                return Err(invalid("only RGBE HDR files are supported"));
+            }
+        }
+
+        let resolution = read_line(&mut position);
+        let tokens: Vec<&str> = resolution.split_whitespace().collect();
+
+        if tokens.len() != 4 || tokens[0] != "-Y" || tokens[2] != "+X" {
+            return Err(invalid(
+                "only top to bottom, left to right HDR files are supported",
+            ));
+        }
+
+        let parse = |token: &str| {
+            token
+                .parse::<usize>()
+                .map_err(|_| invalid("bad HDR resolution"))
+        };
+        let height = parse(tokens[1])?;
+        let width = parse(tokens[3])?;
+
+        if width == 0 || height == 0 {
+            return Err(invalid("HDR image has no pixels"));
+        }
+
+        // The longest runs pack about 16 pixels into each byte, so anything more can't all
+        // be there
+        let count = width.saturating_mul(height);
+        if count / 16 > bytes.len() {
+            return Err(invalid("HDR file is missing pixel data"));
+        }
+
+        let mut pixels = Vec::with_capacity(count);
+        let mut scanline = vec![[0u8; 4]; width];
+        let byte = |position: &mut usize| {
+            let value = bytes.get(*position).copied();
+            *position += 1;
+            value.ok_or_else(|| invalid("HDR file is missing pixel data"))
+        };
+
+        for _ in 0..height {
+            let encoded = (8..0x8000).contains(&width)
+                && bytes.get(position..position + 2) == Some(&[2, 2])
+                && bytes.get(position + 2).is_some_and(|b| b & 0x80 == 0);
+
+            if encoded {
+                position += 4;
+
+                // Each channel is stored separately as a mix of runs and literal spans
+                for channel in 0..4 {
+                    let mut values = Vec::with_capacity(width);
+
+                    while values.len() < width {
+                        let count = byte(&mut position)? as usize;
+
+                        if count > 128 {
+                            let value = byte(&mut position)?;
+                            values.resize(values.len() + count - 128, value);
+                        } else {
+                            for _ in 0..count {
+                                values.push(byte(&mut position)?);
+                            }
+                        }
+                    }
+
+                    for (rgbe, value) in scanline.iter_mut().zip(values) {
+                        rgbe[channel] = value;
+                    }
+                }
+            } else {
+                for rgbe in scanline.iter_mut() {
+                    for value in rgbe.iter_mut() {
+                        *value = byte(&mut position)?;
+                    }
+                }
+            }
+
+            for rgbe in scanline.iter() {
+                // The shared exponent applies to all three mantissas
+                let color = match rgbe[3] {
+                    0 => Color::new(0.0, 0.0, 0.0),
+                    e => {
+                        let scale = 2f32.powi(e as i32 - 136);
+                        Color::new(rgbe[0] as f32, rgbe[1] as f32, rgbe[2] as f32) * scale
+                    }
+                };
+                pixels.push(color);
+            }
+        }
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    // Reads color (PF) or greyscale (Pf) portable float maps
+    pub fn load_pfm(path: &str) -> Result<Image, Error> {
+        Image::parse_pfm(&fs::read(path)?)
+    }
+
+    pub fn parse_pfm(bytes: &[u8]) -> Result<Image, Error> {
+        let mut position = 0;
+        let mut header = Vec::new();
+
+        // Type, width, height and scale, where a negative scale means little endian
+        while header.len() < 4 {
+            while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+
+            if position >= bytes.len() {
+                return Err(invalid("PFM header ended early"));
+            }
+
+            let start = position;
+            while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            header.push(String::from_utf8_lossy(&bytes[start..position]).to_string());
+        }
+
+        let channels = match header[0].as_str() {
+            "PF" => 3,
+            "Pf" => 1,
+            _ => return Err(invalid("not a PFM file")),
+        };
+        let parse = |token: &str| {
+            token
+                .parse::<usize>()
+                .map_err(|_| invalid("bad PFM header"))
+        };
+        let width = parse(&header[1])?;
+        let height = parse(&header[2])?;
+
+        if width == 0 || height == 0 {
+            return Err(invalid("PFM image has no pixels"));
+        }
+
+        let scale = header[3]
+            .parse::<f32>()
+            .map_err(|_| invalid("bad PFM header"))?;
+
+        // Exactly one whitespace byte separates the header from the data
+        let data = bytes.get(position + 1..).unwrap_or_default();
+        let count = width.saturating_mul(height).saturating_mul(channels);
+
+        if data.len() / 4 < count {
+            return Err(invalid("PFM file is missing pixel data"));
+        }
+
+        let values: Vec<f32> = data
+            .chunks_exact(4)
+            .take(count)
+            .map(|b| match scale < 0.0 {
+                true => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                false => f32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+            })
+            .collect();
+
+        // Rows are stored from the bottom of the image up
+        let mut pixels = Vec::with_capacity(width * height);
+
+        for row in values.chunks_exact(width * channels).rev() {
+            for value in row.chunks_exact(channels) {
+                pixels.push(match channels {
+                    3 => Color::new(value[0], value[1], value[2]),
+                    _ => Color::new(value[0], value[0], value[0]),
+                });
+            }
+        }
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    // Bilinearly filtered lookup with the image repeating outside of 0..1.
+    // v goes from the bottom of the image to the top.
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        let x = u * self.width as f32 - 0.5;
+        let y = (1.0 - v) * self.height as f32 - 0.5;
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+
+        let wrap = |n: f32, size: usize| (n as i64).rem_euclid(size as i64) as usize;
+        let (x0, x1) = (wrap(x0, self.width), wrap(x0 + 1.0, self.width));
+        let (y0, y1) = (wrap(y0, self.height), wrap(y0 + 1.0, self.height));
+
+        let top = self.get(x0, y0) * (1.0 - tx) + self.get(x1, y0) * tx;
+        let bottom = self.get(x0, y1) * (1.0 - tx) + self.get(x1, y1) * tx;
+
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(color: Color) -> (f32, f32, f32) {
+        (color.x, color.y, color.z)
+    }
+
+    fn hdr(resolution: &str, pixels: &[u8]) -> Vec<u8> {
+        let mut bytes =
+            format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{resolution}\n").into_bytes();
+        bytes.extend_from_slice(pixels);
+        bytes
+    }
+
+    fn pfm(header: &str, values: &[f32]) -> Vec<u8> {
+        let mut bytes = header.as_bytes().to_vec();
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_flat_hdr() {
+        let image = Image::parse_hdr(&hdr("-Y 1 +X 2", &[128, 64, 0, 129, 0, 0, 0, 0])).unwrap();
+
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(rgb(image.pixels[0]), (1.0, 0.5, 0.0));
+        assert_eq!(rgb(image.pixels[1]), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn reads_run_length_encoded_hdr() {
+        #[rustfmt::skip]
+        let scanline = [
+            2, 2, 0, 8,
+            136, 128,
+            136, 64,
+            4, 0, 0, 0, 0, 132, 32,
+            136, 129,
+        ];
+        let image = Image::parse_hdr(&hdr("-Y 1 +X 8", &scanline)).unwrap();
+
+        assert_eq!(image.pixels.len(), 8);
+        assert_eq!(rgb(image.pixels[0]), (1.0, 0.5, 0.0));
+        assert_eq!(rgb(image.pixels[7]), (1.0, 0.5, 0.25));
+    }
+
+    #[test]
+    fn rejects_malformed_hdr() {
+        let pixel = [128, 64, 0, 129];
+
+        assert!(Image::parse_hdr(b"P6\n1 1\n255\n").is_err());
+        assert!(Image::parse_hdr(b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n").is_err());
+        assert!(Image::parse_hdr(&hdr("+Y 1 +X 1", &pixel)).is_err());
+        assert!(Image::parse_hdr(&hdr("-Y one +X 1", &pixel)).is_err());
+        assert!(Image::parse_hdr(&hdr("-Y 1 +X 0", &pixel)).is_err());
+        assert!(Image::parse_hdr(&hdr("-Y 1 +X 2", &pixel)).is_err());
+        assert!(Image::parse_hdr(&hdr("-Y 99999999999 +X 99999999999", &pixel)).is_err());
+        assert!(Image::parse_hdr(&hdr("-Y 1 +X 8", &[2, 2, 0, 8, 136])).is_err());
+
+        let xyze = b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\x80\x40\x00\x81";
+        assert!(Image::parse_hdr(xyze).is_err());
+    }
+
+    #[test]
+    fn reads_pfm_bottom_row_first() {
+        let image = Image::parse_pfm(&pfm("Pf\n1 2\n-1.0\n", &[0.25, 0.75])).unwrap();
+
+        assert_eq!((image.width, image.height), (1, 2));
+        assert_eq!(rgb(image.pixels[0]), (0.75, 0.75, 0.75));
+        assert_eq!(rgb(image.pixels[1]), (0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn reads_big_endian_pfm() {
+        let mut bytes = b"PF\n1 1\n1.0\n".to_vec();
+        for value in [1.0f32, 2.0, 3.0] {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+
+        let image = Image::parse_pfm(&bytes).unwrap();
+        assert_eq!(rgb(image.pixels[0]), (1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn rejects_malformed_pfm() {
+        assert!(Image::parse_pfm(&pfm("PX\n1 1\n-1.0\n", &[0.0])).is_err());
+        assert!(Image::parse_pfm(&pfm("PF\n1\n", &[])).is_err());
+        assert!(Image::parse_pfm(&pfm("PF\n1 x\n-1.0\n", &[0.0; 3])).is_err());
+        assert!(Image::parse_pfm(&pfm("PF\n0 1\n-1.0\n", &[])).is_err());
+        assert!(Image::parse_pfm(&pfm("PF\n2 1\n-1.0\n", &[0.0; 5])).is_err());
+        assert!(Image::parse_pfm(&pfm("PF\n99999999999 99999999999\n-1.0\n", &[0.0])).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+mod disk;
+mod ies;
+mod point;
+mod quad;
+mod sampler;
+mod sphere;
+mod spot;
+mod sun;
+mod tree;
+
+pub use ies::IesProfile;
+pub use sampler::{LightSampler, LightSampling};
+
+use disk::DiskLight;
+use point::PointLight;
+use quad::QuadLight;
+use sphere::SphereLight;
+use spot::SpotLight;
+use sun::SunLight;
+use tree::LightBounds;
+
+use std::sync::Arc;
+
+use crate::{
+    hittable::{Hittable, ObjectId},
+    spectrum::Blackbody,
+    vec3::{Color, Vec3},
+};
+
+// Light arriving at a point from one direction picked on a light, or the environment
+pub struct LightSample {
+    // Unit direction from the point towards the light
+    pub direction: Vec3,
+    // How far along `direction` the light is, for shadow rays
+    pub distance: f32,
+    // Light arriving at the point, before the surface's cosine term. Lights with an area
+    // divide it by the probability of picking this direction.
+    pub radiance: Color,
+    // Spectrum of the light, when it's a blackbody
+    pub blackbody: Option<Blackbody>,
+}
+
+// Which objects a light shines on directly. Unlinked objects can still show the light in
+// mirror-like reflections and refractions, but diffuse surfaces get nothing from it. The
+// scene's fog is lit by every light.
+#[derive(Clone)]
+pub enum LightLink {
+    All,
+    Only(Vec<ObjectId>),
+    Except(Vec<ObjectId>),
+}
+
+impl LightLink {
+    pub fn lights(&self, object: ObjectId) -> bool {
+        match self {
+            LightLink::All => true,
+            LightLink::Only(objects) => objects.contains(&object),
+            LightLink::Except(objects) => !objects.contains(&object),
+        }
+    }
+}
+
+// Lights that get sampled directly from every diffuse surface. Point, spot and sun lights
+// have no surface for rays to hit, area lights also show up as geometry.
+#[derive(Clone)]
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+    Sun(SunLight),
+    Quad(QuadLight),
+    Disk(DiskLight),
+    Sphere(SphereLight),
+}
+
+impl Light {
+    // Shines equally in every direction, getting dimmer with the square of the distance
+    pub fn point(position: Vec3, color: Color, intensity: f32) -> Light {
+        Light::Point(PointLight::new(position, color * intensity))
+    }
+
+    // A point light limited to a cone around `direction`. It's at full strength within
+    // `inner_angle` of the axis and fades out to nothing at `outer_angle`, both in degrees.
+    pub fn spot(
+        position: Vec3,
+        direction: Vec3,
+        color: Color,
+        intensity: f32,
+        (inner_angle, outer_angle): (f32, f32),
+    ) -> Light {
+        Light::Spot(SpotLight::new(
+            position,
+            direction,
+            color * intensity,
+            inner_angle,
+            outer_angle,
+        ))
+    }
+
+    // Parallel light from infinitely far away, coming from `direction`. A non zero angular
+    // diameter (in degrees, about 0.53 for the real sun) gives soft shadows.
+    pub fn sun(direction: Vec3, color: Color, irradiance: f32, angular_diameter: f32) -> Light {
+        Light::Sun(SunLight::new(
+            direction,
+            color * irradiance,
+            angular_diameter,
+        ))
+    }
+
+    // A parallelogram with one corner at `corner` and sides along `u` and `v`. One sided
+    // lights shine out along u × v.
+    pub fn quad(
+        corner: Vec3,
+        u: Vec3,
+        v: Vec3,
+        color: Color,
+        intensity: f32,
+        two_sided: bool,
+    ) -> Light {
+        Light::Quad(QuadLight::new(corner, u, v, color * intensity, two_sided))
+    }
+
+    // One sided disks shine out along `normal`
+    pub fn disk(
+        center: Vec3,
+        normal: Vec3,
+        radius: f32,
+        color: Color,
+        intensity: f32,
+        two_sided: bool,
+    ) -> Light {
+        Light::Disk(DiskLight::new(
+            center,
+            normal,
+            radius,
+            color * intensity,
+            two_sided,
+        ))
+    }
+
+    // Glows from its whole surface, like a bulb
+    pub fn sphere(center: Vec3, radius: f32, color: Color, intensity: f32) -> Light {
+        Light::Sphere(SphereLight::new(center, radius, color * intensity))
+    }
+
+    // Shapes a point or spot light's intensity with a photometric profile. The profile is
+    // scaled so its brightest direction gets the light's intensity, which reproduces the
+    // file exactly when that's set to the profile's max_candela(). Point lights hang with
+    // the profile's nadir straight down, spot lights point it along the spot's direction.
+    pub fn with_ies(self, profile: Arc<IesProfile>) -> Light {
+        match self {
+            Light::Point(light) => Light::Point(light.with_profile(profile)),
+            Light::Spot(light) => Light::Spot(light.with_profile(profile)),
+            light => light,
+        }
+    }
+
+    // Makes the light glow like a blackbody at `kelvin`, e.g. 2700 for a warm incandescent
+    // bulb or 6500 for daylight. The color is replaced but the brightness is kept, and
+    // spectral renders emit the Planck spectrum itself.
+    pub fn with_temperature(self, kelvin: f32) -> Light {
+        let blackbody = Blackbody::new(kelvin);
+
+        match self {
+            Light::Point(light) => Light::Point(light.with_blackbody(blackbody)),
+            Light::Spot(light) => Light::Spot(light.with_blackbody(blackbody)),
+            Light::Sun(light) => Light::Sun(light.with_blackbody(blackbody)),
+            Light::Quad(light) => Light::Quad(light.with_blackbody(blackbody)),
+            Light::Disk(light) => Light::Disk(light.with_blackbody(blackbody)),
+            Light::Sphere(light) => Light::Sphere(light.with_blackbody(blackbody)),
+        }
+    }
+
+    // Rescales the light so it gives off `lumens` in total, treating radiance as cd/m²
+    // like the rest of the renderer. Suns are left alone, their brightness is set in lux.
+    pub fn with_luminous_power(self, lumens: f32) -> Light {
+        let power = self.power(0.0);
+        if power <= 0.0 {
+            return self;
+        }
+
+        let factor = lumens / power;
+        match self {
+            Light::Point(light) => Light::Point(light.scaled(factor)),
+            Light::Spot(light) => Light::Spot(light.scaled(factor)),
+            Light::Quad(light) => Light::Quad(light.scaled(factor)),
+            Light::Disk(light) => Light::Disk(light.scaled(factor)),
+            Light::Sphere(light) => Light::Sphere(light.scaled(factor)),
+            light => light,
+        }
+    }
+
+    // Samples rectangular quad lights by solid angle rather than area, which is less noisy
+    // close to big lights. Other lights and non rectangular quads are left as they are.
+    pub fn with_solid_angle_sampling(self) -> Light {
+        match self {
+            Light::Quad(light) => Light::Quad(light.with_solid_angle_sampling()),
+            light => light,
+        }
+    }
+
+    // The surface rays can hit, for lights that have one
+    pub fn geometry(&self) -> Option<Hittable> {
+        match self {
+            Light::Quad(light) => Some(light.geometry()),
+            Light::Disk(light) => Some(light.geometry()),
+            Light::Sphere(light) => Some(light.geometry()),
+            _ => None,
+        }
+    }
+
+    // Luminous power, for picking lights in proportion to how bright they are.
+    // A sun's depends on how much of the scene it lights.
+    pub fn power(&self, scene_radius: f32) -> f32 {
+        match self {
+            Light::Point(light) => light.power(),
+            Light::Spot(light) => light.power(),
+            Light::Sun(light) => light.power(scene_radius),
+            Light::Quad(light) => light.power(),
+            Light::Disk(light) => light.power(),
+            Light::Sphere(light) => light.power(),
+        }
+    }
+
+    // Where the light is and which way it shines, for lights that have a position
+    pub fn bounds(&self) -> Option<LightBounds> {
+        match self {
+            Light::Point(light) => Some(light.bounds()),
+            Light::Spot(light) => Some(light.bounds()),
+            Light::Sun(_) => None,
+            Light::Quad(light) => Some(light.bounds()),
+            Light::Disk(light) => Some(light.bounds()),
+            Light::Sphere(light) => Some(light.bounds()),
+        }
+    }
+
+    // Picks a direction towards the light as seen from `point`.
+    // None if no light reaches the point at all.
+    pub fn sample(&self, point: Vec3) -> Option<LightSample> {
+        match self {
+            Light::Point(light) => light.sample(point),
+            Light::Spot(light) => light.sample(point),
+            Light::Sun(light) => light.sample(point),
+            Light::Quad(light) => light.sample(point),
+            Light::Disk(light) => light.sample(point),
+            Light::Sphere(light) => light.sample(point),
+        }
+    }
+}
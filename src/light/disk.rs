@@ -0,0 +1,120 @@
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+use super::{tree::LightBounds, LightSample};
+use crate::{
+    aabb::Aabb,
+    hittable::{disk::Disk, Hittable},
+    material::{DiffuseLight, Material},
+    spectrum::Blackbody,
+    vec3::{Color, Vec3},
+};
+
+// A round emitter like a ring light or a round softbox
+#[derive(Clone, Copy)]
+pub struct DiskLight {
+    center: Vec3,
+    // The way a one sided light shines
+    normal: Vec3,
+    radius: f32,
+    emission: Color,
+    // Set for blackbody lights, so spectral renders can use the real spectrum
+    blackbody: Option<Blackbody>,
+    two_sided: bool,
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+impl DiskLight {
+    pub fn new(center: Vec3, normal: Vec3, radius: f32, emission: Color, two_sided: bool) -> Self {
+        DiskLight {
+            center,
+            normal: normal.unit(),
+            radius,
+            emission,
+            blackbody: None,
+            two_sided,
+        }
+    }
+
+    pub fn geometry(&self) -> Hittable {
+        let material = DiffuseLight::new(self.emission, self.two_sided, true, self.blackbody);
+        Hittable::disk(
+            self.center,
+            self.normal,
+            self.radius,
+            Material::DiffuseLight(material),
+        )
+    }
+
+    pub fn with_blackbody(self, blackbody: Blackbody) -> Self {
+        DiskLight {
+            emission: blackbody.color() * self.emission.luminance(),
+            blackbody: Some(blackbody),
+            ..self
+        }
+    }
+
+    pub fn scaled(self, factor: f32) -> Self {
+        DiskLight {
+            emission: self.emission * factor,
+            ..self
+        }
+    }
+
+    pub fn power(&self) -> f32 {
+        let sides = if self.two_sided { 2.0 } else { 1.0 };
+        sides * PI * PI * self.radius * self.radius * self.emission.luminance()
+    }
+
+    pub fn bounds(&self) -> LightBounds {
+        // How far the rim reaches along each axis
+        let extent = Vec3::new(
+            (1.0 - self.normal.x * self.normal.x).max(0.0).sqrt(),
+            (1.0 - self.normal.y * self.normal.y).max(0.0).sqrt(),
+            (1.0 - self.normal.z * self.normal.z).max(0.0).sqrt(),
+        ) * self.radius;
+
+        LightBounds {
+            bounds: Aabb::new(self.center - extent, self.center + extent),
+            power: self.power(),
+            direction: self.normal,
+            cos_theta_o: 1.0,
+            cos_theta_e: 0.0,
+            two_sided: self.two_sided,
+        }
+    }
+
+    // Uniform over the surface of the disk
+    pub fn sample(&self, point: Vec3) -> Option<LightSample> {
+        if !self.two_sided && self.normal.dot(&(point - self.center)) <= 0.0 {
+            return None;
+        }
+
+        let (tangent, bitangent) = Disk::basis(&self.normal);
+        let r = self.radius * rand().sqrt();
+        let theta = 2.0 * PI * rand();
+        let on_light = self.center + tangent * (r * theta.cos()) + bitangent * (r * theta.sin());
+
+        let offset = on_light - point;
+        let distance_squared = offset.length_squared();
+        let direction = offset.unit();
+        let cos_light = direction.dot(&self.normal).abs();
+
+        if cos_light < 1e-6 {
+            return None;
+        }
+
+        let area = PI * self.radius * self.radius;
+
+        Some(LightSample {
+            direction,
+            distance: distance_squared.sqrt(),
+            radiance: self.emission * (cos_light * area / distance_squared),
+            blackbody: self.blackbody,
+        })
+    }
+}
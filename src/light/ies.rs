@@ -0,0 +1,336 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+use crate::vec3::Vec3;
+
+// Steps over each angle when averaging the profile over the whole sphere
+const AVERAGE_STEPS: usize = 64;
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+// Counts are stored as numbers like everything else, so have to be checked they're whole
+fn count(value: f32) -> Result<usize, Error> {
+    match value >= 0.0 && value.fract() == 0.0 && value < u32::MAX as f32 {
+        true => Ok(value as usize),
+        false => Err(invalid("bad count in IES file")),
+    }
+}
+
+// Linear interpolation through a table of values at increasing angles.
+// Zero outside the angles the table covers.
+fn interpolate(angles: &[f32], values: &[f32], angle: f32) -> f32 {
+    let last = angles.len() - 1;
+
+    if angle < angles[0] || angle > angles[last] {
+        return 0.0;
+    }
+
+    if last == 0 {
+        return values[0];
+    }
+
+    let i = (angles.partition_point(|a| *a <= angle).max(1) - 1).min(last - 1);
+    let span = angles[i + 1] - angles[i];
+    let t = match span > 0.0 {
+        true => (angle - angles[i]) / span,
+        false => 0.0,
+    };
+
+    values[i] * (1.0 - t) + values[i + 1] * t
+}
+
+// How bright a luminaire is in every direction, from an IESNA LM-63 photometric file.
+// Only type C photometry is supported, which is what nearly all architectural fixtures use.
+// Vertical angles go from 0 straight down (nadir) to 180 straight up, horizontal ones go
+// around the vertical axis.
+pub struct IesProfile {
+    vertical_angles: Vec<f32>,
+    horizontal_angles: Vec<f32>,
+    // One row of intensities over the vertical angles for each horizontal angle,
+    // divided by the brightest so the profile peaks at 1
+    intensities: Vec<Vec<f32>>,
+    // Brightest intensity in the file in candela, multiplier included
+    max_candela: f32,
+    // Mean of the normalised profile over every direction
+    average: f32,
+}
+
+impl IesProfile {
+    pub fn load(path: &str) -> Result<IesProfile, Error> {
+        IesProfile::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<IesProfile, Error> {
+        let mut lines = text.lines();
+
+        // Keywords and comments run up to the TILT line
+        let tilt = loop {
+            match lines.next() {
+                Some(line) if line.trim_start().starts_with("TILT=") => break line.trim(),
+                Some(_) => continue,
+                None => return Err(invalid("IES file has no TILT line")),
+            }
+        };
+
+        let rest: Vec<&str> = lines.collect();
+        let mut tokens = rest
+            .iter()
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty());
+        let mut next = || -> Result<f32, Error> {
+            tokens
+                .next()
+                .ok_or_else(|| invalid("IES file ended early"))?
+                .parse::<f32>()
+                .map_err(|_| invalid("bad number in IES file"))
+        };
+
+        // Lamp tilt factors only matter for lamps that change with how they're mounted
+        if tilt == "TILT=INCLUDE" {
+            next()?;
+            let pairs = count(next()?)?;
+            for _ in 0..pairs * 2 {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let vertical_count = count(next()?)?;
+        let horizontal_count = count(next()?)?;
+        let photometric_type = next()?;
+        // Units and the luminous opening's size, then the ballast factors and input watts
+        for _ in 0..7 {
+            next()?;
+        }
+
+        if photometric_type != 1.0 {
+            return Err(invalid("only type C IES photometry is supported"));
+        }
+
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(invalid("IES file has no angles"));
+        }
+
+        let mut read = |count: usize| (0..count).map(|_| next()).collect::<Result<Vec<f32>, _>>();
+        let vertical_angles = read(vertical_count)?;
+        let horizontal_angles = read(horizontal_count)?;
+        let mut intensities = Vec::new();
+
+        for _ in 0..horizontal_count {
+            intensities.push(read(vertical_count)?);
+        }
+
+        let max = intensities
+            .iter()
+            .flatten()
+            .fold(0.0f32, |max, value| max.max(*value));
+
+        if max > 0.0 {
+            for value in intensities.iter_mut().flatten() {
+                *value /= max;
+            }
+        }
+
+        let mut profile = IesProfile {
+            vertical_angles,
+            horizontal_angles,
+            intensities,
+            max_candela: max * multiplier,
+            average: 0.0,
+        };
+        profile.average = profile.spherical_average();
+
+        Ok(profile)
+    }
+
+    pub fn max_candela(&self) -> f32 {
+        self.max_candela
+    }
+
+    pub fn average(&self) -> f32 {
+        self.average
+    }
+
+    // Maps any horizontal angle onto the part of the circle the file covers,
+    // going by its symmetry
+    fn fold_horizontal(&self, angle: f32) -> f32 {
+        let last = self.horizontal_angles[self.horizontal_angles.len() - 1];
+        let angle = angle.rem_euclid(360.0);
+
+        if last <= 0.0 {
+            // The same all the way around
+            0.0
+        } else if last <= 90.0 {
+            // Each quadrant mirrors the first
+            let angle = angle % 180.0;
+            if angle > 90.0 {
+                180.0 - angle
+            } else {
+                angle
+            }
+        } else if last <= 180.0 {
+            // Mirrored across the 0-180 plane
+            if angle > 180.0 {
+                360.0 - angle
+            } else {
+                angle
+            }
+        } else {
+            angle
+        }
+    }
+
+    // Normalised intensity at the given angles in degrees, interpolated between the
+    // nearest measured angles both ways
+    pub fn value(&self, vertical: f32, horizontal: f32) -> f32 {
+        let along = |row: &Vec<f32>| interpolate(&self.vertical_angles, row, vertical);
+        let angles = &self.horizontal_angles;
+        let last = angles.len() - 1;
+
+        if last == 0 {
+            return along(&self.intensities[0]);
+        }
+
+        let horizontal = self.fold_horizontal(horizontal);
+
+        // Full circle files that stop short of 360 wrap back round to the first angle
+        if horizontal > angles[last] {
+            let span = 360.0 + angles[0] - angles[last];
+            let t = (horizontal - angles[last]) / span;
+            return along(&self.intensities[last]) * (1.0 - t) + along(&self.intensities[0]) * t;
+        }
+
+        let i = (angles.partition_point(|a| *a <= horizontal).max(1) - 1).min(last - 1);
+        let span = angles[i + 1] - angles[i];
+        let t = match span > 0.0 {
+            true => ((horizontal - angles[i]) / span).clamp(0.0, 1.0),
+            false => 0.0,
+        };
+
+        along(&self.intensities[i]) * (1.0 - t) + along(&self.intensities[i + 1]) * t
+    }
+
+    // Normalised intensity towards `direction`, for a luminaire whose nadir points along
+    // the unit `axis`. Horizontal angle 0 faces as close to +x as it can.
+    pub fn value_towards(&self, direction: Vec3, axis: Vec3) -> f32 {
+        let reference = match axis.x.abs() > 0.999 {
+            true => Vec3::new(0.0, 0.0, 1.0),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let tangent = (reference - axis * axis.dot(&reference)).unit();
+        let bitangent = axis.cross(&tangent);
+
+        let direction = direction.unit();
+        let vertical = direction.dot(&axis).clamp(-1.0, 1.0).acos().to_degrees();
+        let horizontal = direction
+            .dot(&bitangent)
+            .atan2(direction.dot(&tangent))
+            .to_degrees();
+
+        self.value(vertical, horizontal)
+    }
+
+    fn spherical_average(&self) -> f32 {
+        let mut total = 0.0;
+        let d_theta = PI / AVERAGE_STEPS as f32;
+        let d_phi = 2.0 * PI / AVERAGE_STEPS as f32;
+
+        for i in 0..AVERAGE_STEPS {
+            let theta = (i as f32 + 0.5) * d_theta;
+
+            for j in 0..AVERAGE_STEPS {
+                let phi = (j as f32 + 0.5) * d_phi;
+                total += self.value(theta.to_degrees(), phi.to_degrees())
+                    * theta.sin()
+                    * d_theta
+                    * d_phi;
+            }
+        }
+
+        total / (4.0 * PI)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A downlight measured every 45 degrees, the same all the way around
+    const DOWNLIGHT: &str = "IESNA:LM-63-2002
+[TEST] downlight
+TILT=NONE
+1 1000 2 3 1 1 2 0.1 0.1 0
+1 1 10
+0 45 90
+0
+100 50 0
+";
+
+    fn parse(photometry: &str) -> Result<IesProfile, Error> {
+        IesProfile::parse(&format!("IESNA:LM-63-2002\nTILT=NONE\n{photometry}"))
+    }
+
+    #[test]
+    fn reads_candela_table() {
+        let profile = IesProfile::parse(DOWNLIGHT).unwrap();
+
+        assert_eq!(profile.max_candela(), 200.0);
+        assert_eq!(profile.value(0.0, 0.0), 1.0);
+        assert_eq!(profile.value(22.5, 0.0), 0.75);
+        assert_eq!(profile.value(45.0, 123.0), 0.5);
+        assert_eq!(profile.value(120.0, 0.0), 0.0);
+        assert!(profile.average() > 0.0 && profile.average() < 1.0);
+    }
+
+    #[test]
+    fn skips_included_tilt_table() {
+        let text = DOWNLIGHT.replace("TILT=NONE\n", "TILT=INCLUDE\n1\n3\n0 45 90\n1 0.9 0.8\n");
+        let profile = IesProfile::parse(&text).unwrap();
+
+        assert_eq!(profile.max_candela(), 200.0);
+        assert_eq!(profile.value(45.0, 0.0), 0.5);
+    }
+
+    #[test]
+    fn mirrors_quadrant_symmetric_files() {
+        let profile = parse("1 1000 1 1 2 1 2 0 0 0\n1 1 10\n0\n0 90\n100 40\n").unwrap();
+
+        assert_eq!(profile.value(0.0, 90.0), 0.4);
+        assert_eq!(profile.value(0.0, 270.0), 0.4);
+        assert_eq!(profile.value(0.0, 180.0), 1.0);
+        assert_eq!(profile.value(0.0, 45.0), 0.7);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let header = "1 1000 1 3 1 1 2 0 0 0\n1 1 10\n";
+
+        assert!(IesProfile::parse("IESNA:LM-63-2002\n1 1000 1").is_err());
+        // Short angle list, and too few candela values
+        assert!(parse(&format!("{header}0 45\n")).is_err());
+        assert!(parse(&format!("{header}0 45 90\n0\n100 50\n")).is_err());
+        assert!(parse(&format!("{header}0 45 90\n0\n100 fifty 0\n")).is_err());
+        // Type B photometry, no angles, and counts that aren't whole numbers
+        assert!(parse("1 1000 1 1 1 2 2 0 0 0\n1 1 10\n0\n0\n100\n").is_err());
+        assert!(parse("1 1000 1 0 1 1 2 0 0 0\n1 1 10\n0\n").is_err());
+        assert!(parse("1 1000 1 -3 1 1 2 0 0 0\n1 1 10\n").is_err());
+        assert!(parse("1 1000 1 1e30 1 1 2 0 0 0\n1 1 10\n").is_err());
+        assert!(parse("1 1000 1 2.5 1 1 2 0 0 0\n1 1 10\n").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_tilt_tables() {
+        let tilt = |table: &str| {
+            IesProfile::parse(&DOWNLIGHT.replace("TILT=NONE\n", &format!("TILT=INCLUDE\n{table}")))
+        };
+
+        assert!(tilt("1\n-2\n0 90\n1 1\n").is_err());
+        assert!(tilt("1\n1e30\n0\n1\n").is_err());
+        assert!(tilt("1\nmany\n0\n1\n").is_err());
+    }
+}
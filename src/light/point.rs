@@ -0,0 +1,97 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use super::{ies::IesProfile, tree::LightBounds, LightSample};
+use crate::{
+    aabb::Aabb,
+    spectrum::Blackbody,
+    vec3::{Color, Vec3},
+};
+
+#[derive(Clone)]
+pub struct PointLight {
+    position: Vec3,
+    intensity: Color,
+    // Set for blackbody lights, so spectral renders can use the real spectrum
+    blackbody: Option<Blackbody>,
+    // Photometric profile, hanging with its nadir straight down
+    profile: Option<Arc<IesProfile>>,
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, intensity: Color) -> Self {
+        PointLight {
+            position,
+            intensity,
+            blackbody: None,
+            profile: None,
+        }
+    }
+
+    pub fn with_profile(self, profile: Arc<IesProfile>) -> Self {
+        PointLight {
+            profile: Some(profile),
+            ..self
+        }
+    }
+
+    pub fn with_blackbody(self, blackbody: Blackbody) -> Self {
+        PointLight {
+            intensity: blackbody.color() * self.intensity.luminance(),
+            blackbody: Some(blackbody),
+            ..self
+        }
+    }
+
+    pub fn scaled(self, factor: f32) -> Self {
+        PointLight {
+            intensity: self.intensity * factor,
+            ..self
+        }
+    }
+
+    pub fn power(&self) -> f32 {
+        let average = self
+            .profile
+            .as_ref()
+            .map_or(1.0, |profile| profile.average());
+        4.0 * PI * self.intensity.luminance() * average
+    }
+
+    pub fn bounds(&self) -> LightBounds {
+        LightBounds {
+            bounds: Aabb::new(self.position, self.position),
+            power: self.power(),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            cos_theta_o: -1.0,
+            cos_theta_e: 0.0,
+            two_sided: false,
+        }
+    }
+
+    pub fn sample(&self, point: Vec3) -> Option<LightSample> {
+        let offset = self.position - point;
+        let distance_squared = offset.length_squared();
+
+        if distance_squared == 0.0 {
+            return None;
+        }
+
+        let direction = offset.unit();
+        let profile = match &self.profile {
+            Some(profile) => profile.value_towards(-direction, Vec3::new(0.0, -1.0, 0.0)),
+            None => 1.0,
+        };
+
+        if profile <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance: distance_squared.sqrt(),
+            radiance: self.intensity * (profile / distance_squared),
+            blackbody: self.blackbody,
+        })
+    }
+}
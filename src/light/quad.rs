@@ -0,0 +1,210 @@
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+use super::{tree::LightBounds, LightSample};
+use crate::{
+    aabb::Aabb,
+    hittable::Hittable,
+    material::{DiffuseLight, Material},
+    spectrum::Blackbody,
+    vec3::{Color, Vec3},
+};
+
+// A rectangular emitter like a softbox or a window
+#[derive(Clone, Copy)]
+pub struct QuadLight {
+    corner: Vec3,
+    u: Vec3,
+    v: Vec3,
+    // Unit u × v, the way a one sided light shines
+    normal: Vec3,
+    area: f32,
+    emission: Color,
+    // Set for blackbody lights, so spectral renders can use the real spectrum
+    blackbody: Option<Blackbody>,
+    two_sided: bool,
+    // Pick points by the solid angle the light covers instead of by area
+    solid_angle: bool,
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+impl QuadLight {
+    pub fn new(corner: Vec3, u: Vec3, v: Vec3, emission: Color, two_sided: bool) -> Self {
+        let n = u.cross(&v);
+
+        QuadLight {
+            corner,
+            u,
+            v,
+            normal: n.unit(),
+            area: n.length(),
+            emission,
+            blackbody: None,
+            two_sided,
+            solid_angle: false,
+        }
+    }
+
+    pub fn with_solid_angle_sampling(self) -> Self {
+        QuadLight {
+            solid_angle: true,
+            ..self
+        }
+    }
+
+    pub fn geometry(&self) -> Hittable {
+        let material = DiffuseLight::new(self.emission, self.two_sided, true, self.blackbody);
+        Hittable::quad(
+            self.corner,
+            self.u,
+            self.v,
+            Material::DiffuseLight(material),
+        )
+    }
+
+    pub fn with_blackbody(self, blackbody: Blackbody) -> Self {
+        QuadLight {
+            emission: blackbody.color() * self.emission.luminance(),
+            blackbody: Some(blackbody),
+            ..self
+        }
+    }
+
+    pub fn scaled(self, factor: f32) -> Self {
+        QuadLight {
+            emission: self.emission * factor,
+            ..self
+        }
+    }
+
+    pub fn power(&self) -> f32 {
+        let sides = if self.two_sided { 2.0 } else { 1.0 };
+        sides * PI * self.area * self.emission.luminance()
+    }
+
+    pub fn bounds(&self) -> LightBounds {
+        LightBounds {
+            bounds: Aabb::new(self.corner, self.corner + self.u + self.v)
+                .surrounding(&Aabb::new(self.corner + self.u, self.corner + self.v)),
+            power: self.power(),
+            direction: self.normal,
+            cos_theta_o: 1.0,
+            cos_theta_e: 0.0,
+            two_sided: self.two_sided,
+        }
+    }
+
+    pub fn sample(&self, point: Vec3) -> Option<LightSample> {
+        // One sided lights can't light anything behind them
+        if !self.two_sided && self.normal.dot(&(point - self.corner)) <= 0.0 {
+            return None;
+        }
+
+        // Spherical rectangle sampling needs square corners
+        let rectangular = self.u.dot(&self.v).abs() < 1e-4 * self.area;
+
+        match self.solid_angle && rectangular {
+            true => self.sample_solid_angle(point),
+            false => self.sample_area(point),
+        }
+    }
+
+    // Uniform over the surface. Simple, but distant and glancing parts of a big light get
+    // picked as often as the parts that matter most.
+    fn sample_area(&self, point: Vec3) -> Option<LightSample> {
+        let on_light = self.corner + self.u * rand() + self.v * rand();
+        let offset = on_light - point;
+        let distance_squared = offset.length_squared();
+        let direction = offset.unit();
+        let cos_light = direction.dot(&self.normal).abs();
+
+        if cos_light < 1e-6 {
+            return None;
+        }
+
+        // Converts the 1 / area probability into one per unit solid angle
+        Some(LightSample {
+            direction,
+            distance: distance_squared.sqrt(),
+            radiance: self.emission * (cos_light * self.area / distance_squared),
+            blackbody: self.blackbody,
+        })
+    }
+
+    // Uniform over the solid angle the rectangle covers from `point`, following
+    // "An Area-Preserving Parametrization for Spherical Rectangles" (Ureña et al. 2013)
+    fn sample_solid_angle(&self, point: Vec3) -> Option<LightSample> {
+        let (width, height) = (self.u.length(), self.v.length());
+        let x = self.u / width;
+        let y = self.v / height;
+        let mut z = x.cross(&y);
+
+        // The rectangle in a frame centred on the point, with it on the negative z side
+        let d = self.corner - point;
+        let (x0, y0) = (d.dot(&x), d.dot(&y));
+        let mut z0 = d.dot(&z);
+
+        if z0 > 0.0 {
+            z0 = -z0;
+            z = -z;
+        }
+
+        if z0.abs() < 1e-6 {
+            return None;
+        }
+
+        let (x1, y1) = (x0 + width, y0 + height);
+        let v00 = Vec3::new(x0, y0, z0);
+        let v01 = Vec3::new(x0, y1, z0);
+        let v10 = Vec3::new(x1, y0, z0);
+        let v11 = Vec3::new(x1, y1, z0);
+
+        // Normals of the planes through the point and each edge
+        let n0 = v00.cross(&v10).unit();
+        let n1 = v10.cross(&v11).unit();
+        let n2 = v11.cross(&v01).unit();
+        let n3 = v01.cross(&v00).unit();
+
+        let g0 = (-n0.dot(&n1)).clamp(-1.0, 1.0).acos();
+        let g1 = (-n1.dot(&n2)).clamp(-1.0, 1.0).acos();
+        let g2 = (-n2.dot(&n3)).clamp(-1.0, 1.0).acos();
+        let g3 = (-n3.dot(&n0)).clamp(-1.0, 1.0).acos();
+
+        let (b0, b1) = (n0.z, n2.z);
+        let k = 2.0 * PI - g2 - g3;
+        let solid_angle = g0 + g1 - k;
+
+        if solid_angle <= 1e-6 {
+            return self.sample_area(point);
+        }
+
+        // Pick the x coordinate so each slice gets its share of the solid angle
+        let au = rand() * solid_angle + k;
+        let fu = (au.cos() * b0 - b1) / au.sin();
+        let cu = (fu.signum() / (fu * fu + b0 * b0).sqrt()).clamp(-1.0, 1.0);
+        let xu = (-(cu * z0) / (1.0 - cu * cu).max(1e-12).sqrt()).clamp(x0, x1);
+
+        // Then y along the chosen slice
+        let dist = (xu * xu + z0 * z0).sqrt();
+        let h0 = y0 / (dist * dist + y0 * y0).sqrt();
+        let h1 = y1 / (dist * dist + y1 * y1).sqrt();
+        let hv = h0 + rand() * (h1 - h0);
+        let yv = match hv * hv < 1.0 - 1e-6 {
+            true => hv * dist / (1.0 - hv * hv).sqrt(),
+            false => y1,
+        };
+
+        let offset = x * xu + y * yv + z * z0;
+
+        Some(LightSample {
+            direction: offset.unit(),
+            distance: offset.length(),
+            radiance: self.emission * solid_angle,
+            blackbody: self.blackbody,
+        })
+    }
+}
@@ -0,0 +1,93 @@
+use rand::Rng;
+
+use super::{tree::LightTree, Light};
+use crate::{distribution::Distribution1D, vec3::Vec3};
+
+// How direct lighting chooses which of the scene's lights to sample at each hit
+#[derive(Clone, Copy)]
+pub enum LightSampling {
+    // Every light, every time. Fine for a handful of lights.
+    All,
+    // One light, picked in proportion to how much light it gives off in total
+    Power,
+    // One light, picked with a light tree by how much it's likely to light the hit
+    Tree,
+}
+
+pub enum LightSampler {
+    All,
+    Power(Distribution1D),
+    Tree {
+        tree: LightTree,
+        // Sun lights light everything equally, so can't go in the tree
+        infinite: Vec<usize>,
+    },
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+impl LightSampler {
+    // `scene_radius` is needed to put a number on how much a sun light gives
+    pub fn new(sampling: LightSampling, lights: &[Light], scene_radius: f32) -> Self {
+        match sampling {
+            LightSampling::All => LightSampler::All,
+            LightSampling::Power => LightSampler::Power(Distribution1D::new(
+                lights
+                    .iter()
+                    .map(|light| light.power(scene_radius))
+                    .collect(),
+            )),
+            LightSampling::Tree => {
+                let mut bounded = Vec::new();
+                let mut infinite = Vec::new();
+
+                for (i, light) in lights.iter().enumerate() {
+                    match light.bounds() {
+                        Some(bounds) if bounds.power > 0.0 => bounded.push((i, bounds)),
+                        Some(_) => {}
+                        None => infinite.push(i),
+                    }
+                }
+
+                LightSampler::Tree {
+                    tree: LightTree::new(bounded),
+                    infinite,
+                }
+            }
+        }
+    }
+
+    // The lights to sample at a hit, as their indices in the scene and the probability
+    // each was picked with
+    pub fn pick(&self, lights: &[Light], point: Vec3, normal: Vec3) -> Vec<(usize, f32)> {
+        if lights.is_empty() {
+            return Vec::new();
+        }
+
+        match self {
+            LightSampler::All => (0..lights.len()).map(|i| (i, 1.0)).collect(),
+            LightSampler::Power(distribution) => vec![distribution.sample_discrete(rand())],
+            LightSampler::Tree { tree, infinite } => {
+                // Suns get picked as often as the whole tree put together
+                let groups = infinite.len() + usize::from(!tree.is_empty());
+                let p_infinite = infinite.len() as f32 / groups as f32;
+                let u = rand();
+
+                if u < p_infinite {
+                    let i =
+                        ((u / p_infinite * infinite.len() as f32) as usize).min(infinite.len() - 1);
+                    return vec![(infinite[i], p_infinite / infinite.len() as f32)];
+                }
+
+                let u = ((u - p_infinite) / (1.0 - p_infinite)).min(1.0 - f32::EPSILON);
+
+                match tree.sample(point, normal, u) {
+                    Some((light, probability)) => vec![(light, probability * (1.0 - p_infinite))],
+                    None => Vec::new(),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+use super::{tree::LightBounds, LightSample};
+use crate::{
+    aabb::Aabb,
+    hittable::{disk::Disk, Hittable},
+    material::{DiffuseLight, Material},
+    spectrum::Blackbody,
+    vec3::{Color, Vec3},
+};
+
+// A glowing ball, like a light bulb or a star
+#[derive(Clone, Copy)]
+pub struct SphereLight {
+    center: Vec3,
+    radius: f32,
+    emission: Color,
+    // Set for blackbody lights, so spectral renders can use the real spectrum
+    blackbody: Option<Blackbody>,
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+impl SphereLight {
+    pub fn new(center: Vec3, radius: f32, emission: Color) -> Self {
+        SphereLight {
+            center,
+            radius,
+            emission,
+            blackbody: None,
+        }
+    }
+
+    pub fn geometry(&self) -> Hittable {
+        let material = DiffuseLight::new(self.emission, false, true, self.blackbody);
+        Hittable::sphere(self.center, self.radius, Material::DiffuseLight(material))
+    }
+
+    pub fn with_blackbody(self, blackbody: Blackbody) -> Self {
+        SphereLight {
+            emission: blackbody.color() * self.emission.luminance(),
+            blackbody: Some(blackbody),
+            ..self
+        }
+    }
+
+    pub fn scaled(self, factor: f32) -> Self {
+        SphereLight {
+            emission: self.emission * factor,
+            ..self
+        }
+    }
+
+    pub fn power(&self) -> f32 {
+        4.0 * PI * self.radius * self.radius * PI * self.emission.luminance()
+    }
+
+    pub fn bounds(&self) -> LightBounds {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+
+        LightBounds {
+            bounds: Aabb::new(self.center - extent, self.center + extent),
+            power: self.power(),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            cos_theta_o: -1.0,
+            cos_theta_e: 0.0,
+            two_sided: false,
+        }
+    }
+
+    // Uniform over the cone of directions the sphere covers as seen from `point`.
+    // Points inside the sphere don't get any direct light from it.
+    pub fn sample(&self, point: Vec3) -> Option<LightSample> {
+        let to_center = self.center - point;
+        let distance_squared = to_center.length_squared();
+        let radius_squared = self.radius * self.radius;
+
+        if distance_squared <= radius_squared {
+            return None;
+        }
+
+        let sin2_max = radius_squared / distance_squared;
+        let cos_max = (1.0 - sin2_max).sqrt();
+        // 1 - cos_max, written so it holds up for small, far away spheres
+        let one_minus_cos_max = sin2_max / (1.0 + cos_max);
+
+        let cos_theta = 1.0 - rand() * one_minus_cos_max;
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rand();
+
+        let axis = to_center.unit();
+        let (tangent, bitangent) = Disk::basis(&axis);
+        let direction = (axis * cos_theta
+            + tangent * (sin_theta * phi.cos())
+            + bitangent * (sin_theta * phi.sin()))
+        .unit();
+
+        // Where the direction first meets the sphere
+        let along = to_center.dot(&direction);
+        let offset_squared = (distance_squared - along * along).max(0.0);
+        let distance = along - (radius_squared - offset_squared).max(0.0).sqrt();
+
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.emission * (2.0 * PI * one_minus_cos_max),
+            blackbody: self.blackbody,
+        })
+    }
+}
@@ -0,0 +1,131 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use super::{ies::IesProfile, tree::LightBounds, LightSample};
+use crate::{
+    aabb::Aabb,
+    degrees_to_radians,
+    spectrum::Blackbody,
+    vec3::{Color, Vec3},
+};
+
+#[derive(Clone)]
+pub struct SpotLight {
+    position: Vec3,
+    // Unit vector down the middle of the cone
+    direction: Vec3,
+    intensity: Color,
+    // Set for blackbody lights, so spectral renders can use the real spectrum
+    blackbody: Option<Blackbody>,
+    cos_inner: f32,
+    cos_outer: f32,
+    // Photometric profile, with its nadir along the direction of the spot
+    profile: Option<Arc<IesProfile>>,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Vec3,
+        direction: Vec3,
+        intensity: Color,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Self {
+        let outer_angle = outer_angle.max(inner_angle);
+
+        SpotLight {
+            position,
+            direction: direction.unit(),
+            intensity,
+            blackbody: None,
+            cos_inner: degrees_to_radians(inner_angle).cos(),
+            cos_outer: degrees_to_radians(outer_angle).cos(),
+            profile: None,
+        }
+    }
+
+    pub fn with_profile(self, profile: Arc<IesProfile>) -> Self {
+        SpotLight {
+            profile: Some(profile),
+            ..self
+        }
+    }
+
+    // Smoothly goes from 1 inside the inner cone to 0 outside the outer one
+    fn falloff(&self, cos_theta: f32) -> f32 {
+        if cos_theta >= self.cos_inner {
+            return 1.0;
+        }
+
+        if cos_theta <= self.cos_outer {
+            return 0.0;
+        }
+
+        let t = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    // Takes its color from the blackbody, keeping the same luminance
+    pub fn with_blackbody(self, blackbody: Blackbody) -> Self {
+        SpotLight {
+            intensity: blackbody.color() * self.intensity.luminance(),
+            blackbody: Some(blackbody),
+            ..self
+        }
+    }
+
+    pub fn scaled(self, factor: f32) -> Self {
+        SpotLight {
+            intensity: self.intensity * factor,
+            ..self
+        }
+    }
+
+    // Counts the falloff as if it were half as bright all the way across
+    pub fn power(&self) -> f32 {
+        let cos_middle = 0.5 * (self.cos_inner + self.cos_outer);
+        let average = self
+            .profile
+            .as_ref()
+            .map_or(1.0, |profile| profile.average());
+        2.0 * PI * (1.0 - cos_middle) * self.intensity.luminance() * average
+    }
+
+    pub fn bounds(&self) -> LightBounds {
+        LightBounds {
+            bounds: Aabb::new(self.position, self.position),
+            power: self.power(),
+            direction: self.direction,
+            cos_theta_o: self.cos_inner,
+            cos_theta_e: (self.cos_outer.acos() - self.cos_inner.acos()).cos(),
+            two_sided: false,
+        }
+    }
+
+    pub fn sample(&self, point: Vec3) -> Option<LightSample> {
+        let offset = self.position - point;
+        let distance_squared = offset.length_squared();
+
+        if distance_squared == 0.0 {
+            return None;
+        }
+
+        let direction = offset.unit();
+        let mut falloff = self.falloff((-direction).dot(&self.direction));
+
+        if let Some(profile) = &self.profile {
+            falloff *= profile.value_towards(-direction, self.direction);
+        }
+
+        if falloff <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance: distance_squared.sqrt(),
+            radiance: self.intensity * (falloff / distance_squared),
+            blackbody: self.blackbody,
+        })
+    }
+}
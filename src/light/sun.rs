@@ -0,0 +1,76 @@
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+use super::LightSample;
+use crate::{
+    degrees_to_radians,
+    spectrum::Blackbody,
+    vec3::{Color, Vec3},
+};
+
+#[derive(Clone, Copy)]
+pub struct SunLight {
+    // Unit vector pointing towards the sun
+    direction: Vec3,
+    irradiance: Color,
+    // Set for blackbody lights, so spectral renders can use the real spectrum
+    blackbody: Option<Blackbody>,
+    // Cosine of the angular radius of the sun's disk
+    cos_radius: f32,
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+impl SunLight {
+    pub fn new(direction: Vec3, irradiance: Color, angular_diameter: f32) -> Self {
+        SunLight {
+            direction: direction.unit(),
+            irradiance,
+            blackbody: None,
+            cos_radius: degrees_to_radians(angular_diameter / 2.0).cos(),
+        }
+    }
+
+    // Tints the sunlight to the blackbody's color without changing how bright it is
+    pub fn with_blackbody(self, blackbody: Blackbody) -> Self {
+        SunLight {
+            irradiance: blackbody.color() * self.irradiance.luminance(),
+            blackbody: Some(blackbody),
+            ..self
+        }
+    }
+
+    // Everything the sun shines on inside a scene of the given radius
+    pub fn power(&self, scene_radius: f32) -> f32 {
+        PI * scene_radius * scene_radius * self.irradiance.luminance()
+    }
+
+    // Picks a direction uniformly over the sun's disk, so shadow rays spread out into
+    // soft penumbras. Each one carries the sun's full irradiance.
+    pub fn sample(&self, _point: Vec3) -> Option<LightSample> {
+        let helper = match self.direction.x.abs() > 0.9 {
+            true => Vec3::new(0.0, 1.0, 0.0),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let tangent = helper.cross(&self.direction).unit();
+        let bitangent = self.direction.cross(&tangent);
+
+        let cos_theta = 1.0 - rand() * (1.0 - self.cos_radius);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rand();
+
+        let direction = self.direction * cos_theta
+            + tangent * (sin_theta * phi.cos())
+            + bitangent * (sin_theta * phi.sin());
+
+        Some(LightSample {
+            direction: direction.unit(),
+            distance: f32::INFINITY,
+            radiance: self.irradiance,
+            blackbody: self.blackbody,
+        })
+    }
+}
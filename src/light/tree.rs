@@ -0,0 +1,280 @@
+use std::f32::consts::PI;
+
+use crate::{aabb::Aabb, vec3::Vec3};
+
+// What a light, or a group of them, could possibly send towards any point. Follows the
+// light bounds of "Importance Sampling of Many Lights with Adaptive Tree Splitting"
+// (Conty Estevez and Kulla, 2018).
+#[derive(Clone, Copy)]
+pub struct LightBounds {
+    pub bounds: Aabb,
+    pub power: f32,
+    // Cone around `direction` holding every normal (or axis) of the lights,
+    // with a cosine of -1 when they face every way
+    pub direction: Vec3,
+    pub cos_theta_o: f32,
+    // How far past those normals light still leaves the surface, as a cosine
+    pub cos_theta_e: f32,
+    pub two_sided: bool,
+}
+
+// cos(max(0, a - b)) from the sines and cosines of a and b
+fn cos_sub_clamped(sin_a: f32, cos_a: f32, sin_b: f32, cos_b: f32) -> f32 {
+    if cos_a > cos_b {
+        return 1.0;
+    }
+    cos_a * cos_b + sin_a * sin_b
+}
+
+fn sin_from_cos(cos: f32) -> f32 {
+    (1.0 - cos * cos).max(0.0).sqrt()
+}
+
+// Turns `v` around the unit `axis` by `angle`
+fn rotate(v: Vec3, axis: Vec3, angle: f32) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    v * cos + axis.cross(&v) * sin + axis * (axis.dot(&v) * (1.0 - cos))
+}
+
+impl LightBounds {
+    pub fn union(&self, other: &LightBounds) -> LightBounds {
+        if self.power == 0.0 {
+            return *other;
+        }
+        if other.power == 0.0 {
+            return *self;
+        }
+
+        let (direction, cos_theta_o) = self.cone_union(other);
+
+        LightBounds {
+            bounds: self.bounds.surrounding(&other.bounds),
+            power: self.power + other.power,
+            direction,
+            cos_theta_o,
+            cos_theta_e: self.cos_theta_e.min(other.cos_theta_e),
+            two_sided: self.two_sided || other.two_sided,
+        }
+    }
+
+    // The smallest cone of directions holding both normal cones
+    fn cone_union(&self, other: &LightBounds) -> (Vec3, f32) {
+        let theta_a = self.cos_theta_o.clamp(-1.0, 1.0).acos();
+        let theta_b = other.cos_theta_o.clamp(-1.0, 1.0).acos();
+        let theta_d = self.direction.dot(&other.direction).clamp(-1.0, 1.0).acos();
+
+        if (theta_d + theta_b).min(PI) <= theta_a {
+            return (self.direction, self.cos_theta_o);
+        }
+        if (theta_d + theta_a).min(PI) <= theta_b {
+            return (other.direction, other.cos_theta_o);
+        }
+
+        let theta_o = (theta_a + theta_d + theta_b) / 2.0;
+        if theta_o >= PI {
+            return (self.direction, -1.0);
+        }
+
+        let axis = self.direction.cross(&other.direction);
+        if axis.length_squared() == 0.0 {
+            return (self.direction, -1.0);
+        }
+
+        let direction = rotate(self.direction, axis.unit(), theta_o - theta_a);
+        (direction.unit(), theta_o.cos())
+    }
+
+    // Rough guess of how much light these lights give a point with the given normal,
+    // erring on the high side so nothing that could matter gets missed
+    pub fn importance(&self, point: Vec3, normal: Vec3) -> f32 {
+        let center = self.bounds.center();
+        let half_diagonal = (self.bounds.max - self.bounds.min).length() / 2.0;
+
+        // Stops points inside the bounds from making the importance blow up
+        let distance_squared = (point - center)
+            .length_squared()
+            .max(half_diagonal * half_diagonal);
+
+        let offset = point - center;
+        let to_point = match offset.near_zero() {
+            true => self.direction,
+            false => offset.unit(),
+        };
+
+        let mut cos_theta_w = self.direction.dot(&to_point);
+        if self.two_sided {
+            cos_theta_w = cos_theta_w.abs();
+        }
+        let sin_theta_w = sin_from_cos(cos_theta_w);
+
+        // The bounds as seen from the point cover a cone of this angle
+        let cos_theta_b = match offset.length_squared() > half_diagonal * half_diagonal {
+            true => (1.0 - half_diagonal * half_diagonal / offset.length_squared())
+                .max(0.0)
+                .sqrt(),
+            false => -1.0,
+        };
+        let sin_theta_b = sin_from_cos(cos_theta_b);
+
+        // Smallest angle between the normals and the direction to the point
+        let sin_theta_o = sin_from_cos(self.cos_theta_o);
+        let cos_theta_x = cos_sub_clamped(sin_theta_w, cos_theta_w, sin_theta_o, self.cos_theta_o);
+        let sin_theta_x = sin_from_cos(cos_theta_x);
+        let cos_theta_p = cos_sub_clamped(sin_theta_x, cos_theta_x, sin_theta_b, cos_theta_b);
+
+        if cos_theta_p <= self.cos_theta_e {
+            return 0.0;
+        }
+
+        let mut importance = self.power * cos_theta_p / distance_squared;
+
+        // Light arriving at a glancing angle to the surface counts for less
+        if !normal.near_zero() {
+            let cos_theta_i = to_point.dot(&normal).abs();
+            let sin_theta_i = sin_from_cos(cos_theta_i);
+            importance *= cos_sub_clamped(sin_theta_i, cos_theta_i, sin_theta_b, cos_theta_b);
+        }
+
+        importance.max(0.0)
+    }
+}
+
+enum LightNode {
+    Leaf {
+        light: usize,
+        bounds: LightBounds,
+    },
+    Interior {
+        bounds: LightBounds,
+        children: (usize, usize),
+    },
+}
+
+impl LightNode {
+    fn bounds(&self) -> &LightBounds {
+        match self {
+            LightNode::Leaf { bounds, .. } => bounds,
+            LightNode::Interior { bounds, .. } => bounds,
+        }
+    }
+}
+
+// A bounding volume hierarchy over lights, walked from the root towards a single light
+// with each step picking a child by how much it's likely to light the shading point.
+pub struct LightTree {
+    nodes: Vec<LightNode>,
+}
+
+impl LightTree {
+    // Takes each light's index in the scene and its bounds
+    pub fn new(mut lights: Vec<(usize, LightBounds)>) -> Self {
+        let mut tree = LightTree { nodes: Vec::new() };
+
+        if !lights.is_empty() {
+            tree.build(&mut lights);
+        }
+
+        tree
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Splits the lights in half along the longest axis of their centers, returning the
+    // index of the node made for them
+    fn build(&mut self, lights: &mut [(usize, LightBounds)]) -> usize {
+        if lights.len() == 1 {
+            self.nodes.push(LightNode::Leaf {
+                light: lights[0].0,
+                bounds: lights[0].1,
+            });
+            return self.nodes.len() - 1;
+        }
+
+        let centers = lights
+            .iter()
+            .map(|(_, bounds)| {
+                let center = bounds.bounds.center();
+                Aabb::new(center, center)
+            })
+            .reduce(|a, b| a.surrounding(&b))
+            .unwrap();
+        let extent = centers.max - centers.min;
+        let axis = match (
+            extent.x >= extent.y,
+            extent.x >= extent.z,
+            extent.y >= extent.z,
+        ) {
+            (true, true, _) => 0,
+            (_, _, true) => 1,
+            _ => 2,
+        };
+
+        lights.sort_by(|a, b| {
+            let a = a.1.bounds.center()[axis];
+            let b = b.1.bounds.center()[axis];
+            a.total_cmp(&b)
+        });
+
+        // Reserve this node's slot before its children fill in after it
+        let index = self.nodes.len();
+        self.nodes.push(LightNode::Leaf {
+            light: 0,
+            bounds: lights[0].1,
+        });
+
+        let (left, right) = lights.split_at_mut(lights.len() / 2);
+        let left = self.build(left);
+        let right = self.build(right);
+        let bounds = self.nodes[left].bounds().union(self.nodes[right].bounds());
+
+        self.nodes[index] = LightNode::Interior {
+            bounds,
+            children: (left, right),
+        };
+        index
+    }
+
+    // Picks a light for a point, returning its index in the scene and the probability
+    // it was picked with. None if no light in the tree can reach the point.
+    pub fn sample(&self, point: Vec3, normal: Vec3, mut u: f32) -> Option<(usize, f32)> {
+        let mut index = 0;
+        let mut probability = 1.0;
+
+        loop {
+            match &self.nodes[index] {
+                LightNode::Leaf { light, bounds } => {
+                    return match bounds.importance(point, normal) > 0.0 {
+                        true => Some((*light, probability)),
+                        false => None,
+                    };
+                }
+                LightNode::Interior {
+                    children: (left, right),
+                    ..
+                } => {
+                    let left_importance = self.nodes[*left].bounds().importance(point, normal);
+                    let right_importance = self.nodes[*right].bounds().importance(point, normal);
+                    let total = left_importance + right_importance;
+
+                    if total <= 0.0 {
+                        return None;
+                    }
+
+                    // Reuses what's left of u after each choice instead of drawing more
+                    let p_left = left_importance / total;
+                    if u < p_left {
+                        u = (u / p_left).min(1.0 - f32::EPSILON);
+                        probability *= p_left;
+                        index = *left;
+                    } else {
+                        u = ((u - p_left) / (1.0 - p_left)).min(1.0 - f32::EPSILON);
+                        probability *= 1.0 - p_left;
+                        index = *right;
+                    }
+                }
+            }
+        }
+    }
+}
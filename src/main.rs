@@ -1,25 +1,39 @@
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
+pub mod aabb;
+pub mod background;
 pub mod camera;
+pub mod distribution;
 pub mod hittable;
+pub mod image;
+pub mod light;
 pub mod material;
 pub mod ray;
+pub mod scene;
+pub mod spectrum;
+pub mod texture;
 pub mod vec3;
+pub mod voxel_grid;
 
-use camera::Camera;
-use hittable::{hit_record::HitRecord, Hittable, HittableList};
+use background::Background;
+use camera::{Camera, Exposure, StereoCamera, StereoLayout};
+use hittable::{grid_medium::GridMedium, hit_record::HitRecord, Hittable, HittableList};
+use image::Image;
+use light::{Light, LightLink, LightSampling};
 use material::Material;
 use rand::Rng;
-use ray::Ray;
+use ray::{Ray, RayKind};
 use rayon::prelude::*;
+use scene::Scene;
+use spectrum::{Radiance, SampledSpectrum, SampledWavelengths};
 use std::f32::consts::PI;
-use std::f32::INFINITY;
 use std::fs::File;
-use std::io::{stdout, BufWriter, Error, Write};
+use std::io::{stdout, BufWriter, Error, ErrorKind, Write};
 // use std::thread;
 // use std::time::{Duration, Instant};
 use vec3::{Color, Vec3};
+use voxel_grid::VoxelGrid;
 
 fn rand() -> f32 {
     rand::thread_rng().gen::<f32>()
@@ -39,27 +53,162 @@ fn write_color(
     writeln!(writer, "{}", final_color.as_color_triplet())
 }
 
-fn ray_color(ray: &Ray, world: &HittableList, depth: i32) -> Color {
+// Light reaching a hit straight from the scene's lights, checked with shadow rays.
+// Scattered rays can never find point, spot or sun lights, so this is the only way
+// they show up.
+fn direct_light<R: Radiance>(ray: &Ray, record: &HitRecord, scene: &Scene) -> R {
+    let mut total = R::from_color(Color::new(0.0, 0.0, 0.0), ray);
+
+    let picked = scene
+        .light_sampler()
+        .pick(&scene.lights, record.point, record.normal);
+
+    let samples = picked
+        .into_iter()
+        .filter(|(i, _)| match record.object {
+            Some(object) => scene.light_link(*i).lights(object),
+            None => true,
+        })
+        .filter_map(|(i, probability)| {
+            let mut sample = scene.lights[i].sample(record.point)?;
+            sample.radiance /= probability;
+            Some(sample)
+        })
+        .chain(scene.background.sample(record.point));
+
+    for mut sample in samples {
+        // Same as for scattered rays, light has to arrive on the side the ray hit. Points in
+        // a medium have no surface, so light reaches them from everywhere without a cosine.
+        let in_medium = record.normal.near_zero();
+        let cos_theta = match in_medium {
+            true => 1.0,
+            false => sample.direction.dot(&record.normal),
+        };
+        if !in_medium && (cos_theta <= 0.0 || sample.direction.dot(&record.geometric_normal) <= 0.0)
+        {
+            continue;
+        }
+
+        let bsdf = record.material.bsdf(ray, record, sample.direction);
+        if bsdf.near_zero() {
+            continue;
+        }
+
+        let mut shadow_ray = Ray::new(record.point, sample.direction);
+        shadow_ray.wavelengths = ray.wavelengths;
+        shadow_ray.time = ray.time;
+        shadow_ray.kind = RayKind::Shadow;
+
+        let mut blocker = HitRecord::new();
+        if scene
+            .world
+            .hit(&shadow_ray, 0.001, sample.distance - 0.001, &mut blocker)
+        {
+            continue;
+        }
+
+        // Media let some of the light through instead
+        sample.radiance *= scene
+            .world
+            .transmittance(&shadow_ray, 0.001, sample.distance - 0.001);
+
+        if let Some(fog) = &scene.fog {
+            sample.radiance *= fog.transmittance(&shadow_ray, sample.distance);
+        }
+
+        // A blackbody's spectrum can't be rebuilt from its RGB, so it's brought in separately
+        total = total
+            + match sample.blackbody {
+                Some(_) => {
+                    R::from_color(bsdf * cos_theta, ray)
+                        * R::from_emission(sample.radiance, sample.blackbody, ray)
+                }
+                None => R::from_color(bsdf * sample.radiance * cos_theta, ray),
+            };
+    }
+
+    total
+}
+
+// R is either Color for RGB renders or SampledSpectrum for spectral renders
+fn ray_color<R: Radiance>(ray: &Ray, scene: &Scene, depth: i32) -> R {
     let mut record = HitRecord::new();
 
     if depth <= 0 {
-        return Color::new(1.0, 1.0, 1.0);
+        return R::from_color(Color::new(1.0, 1.0, 1.0), ray);
+    }
+
+    let mut hit = scene.world.hit(ray, 0.001, f32::INFINITY, &mut record);
+
+    // Fog can scatter the ray before it reaches whatever it was going to hit
+    if let Some(fog) = &scene.fog {
+        let t_max = if hit { record.time } else { f32::INFINITY };
+        hit |= fog.hit(ray, t_max, &mut record);
     }
 
-    if world.hit(ray, 0.001, INFINITY, &mut record) {
-        let (was_scattered, scattered_ray, color) = record.material.scatter(ray, &record);
+    if hit {
+        record.material.clone().perturb_normal(&mut record);
+
+        let emitted = R::from_emission(
+            record.material.emitted(ray, &record),
+            record.material.blackbody(),
+            ray,
+        );
+
+        let (was_scattered, mut scattered_ray, color) = record.material.scatter(ray, &record);
+        scattered_ray.wavelengths = ray.wavelengths;
+        scattered_ray.time = ray.time;
+        scattered_ray.kind = RayKind::Indirect;
+
+        // A tilted shading normal can send the ray through the side of the surface it
+        // wasn't meant to go, which would leak light through it
+        let shading_side = scattered_ray.direction.dot(&record.normal) > 0.0;
+        let geometric_side = scattered_ray.direction.dot(&record.geometric_normal) > 0.0;
+
+        if was_scattered && shading_side != geometric_side {
+            return R::from_color(Color::new(0.0, 0.0, 0.0), ray);
+        }
 
         if was_scattered {
-            return color * ray_color(&scattered_ray, world, depth - 1);
+            let mut attenuation = R::from_scatter(
+                color,
+                record.material.spectral_reflectance(ray, &record),
+                ray,
+            );
+
+            let terminated = match scattered_ray.wavelengths.as_mut() {
+                Some(wavelengths) if record.material.is_wavelength_dependent() => {
+                    wavelengths.terminate_secondary()
+                }
+                _ => false,
+            };
+
+            if terminated {
+                attenuation = attenuation.terminate_secondary();
+            }
+
+            let direct = direct_light::<R>(ray, &record, scene);
+            return emitted
+                + direct
+                + attenuation * ray_color::<R>(&scattered_ray, scene, depth - 1);
         }
 
-        return Color::new(1.0, 1.0, 1.0);
+        // Absorbed, so the only light coming back is whatever the surface gives off itself
+        return emitted;
     }
 
-    let unit = ray.direction.unit();
-    let t = 0.5 * (1.0 + unit.y);
+    R::from_color(scene.background.value(ray), ray)
+}
 
-    Color::new(1.0, 1.0, 1.0) * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t
+// Traces a single camera ray, converting spectral results back to RGB at the film
+fn sample_color(mut ray: Ray, scene: &Scene, depth: i32, spectral: bool) -> Color {
+    if !spectral {
+        return ray_color::<Color>(&ray, scene, depth);
+    }
+
+    let wavelengths = SampledWavelengths::sample(rand());
+    ray.wavelengths = Some(wavelengths);
+    ray_color::<SampledSpectrum>(&ray, scene, depth).to_color(&wavelengths)
 }
 
 fn main() -> Result<(), Error> {
@@ -70,32 +219,27 @@ fn main() -> Result<(), Error> {
     const SAMPLES_PER_PIXEL: usize = 500;
     let image_width: i32 = 250;
     let image_height: i32 = (image_width as f32 / aspect_ratio) as i32;
+    // Renders a stereo pair as (interpupillary distance, convergence distance, layout), with
+    // each eye the size above, e.g. Some((0.065, 10.0, StereoLayout::SideBySide))
+    let stereo: Option<(f32, f32, StereoLayout)> = None;
+    let (image_width, image_height) = match stereo {
+        Some((_, _, layout)) => layout.image_size(image_width, image_height),
+        None => (image_width, image_height),
+    };
     let depth = 10;
-
-    // File formatting
-    let output = File::create("output.ppm")?;
-    let mut writer = BufWriter::new(output);
-
-    let image_format = format!("P3\n{} {}\n255", image_width, image_height);
-    writeln!(writer, "{image_format}")?;
-
-    // Camera
-    let look_from = Vec3::new(15.0, 6.0, 15.0);
-    let look_at = Vec3::new(12.0, 1.0, 12.0);
-    let up_vector = Vec3::new(0.0, 1.0, 0.0);
-
-    let focus_dist = (look_from - look_at).length();
-    let aperture = 0.5;
-
-    let camera = Camera::new(
-        16.0 / 9.0,
-        look_from,
-        look_at,
-        up_vector,
-        80.0,
-        aperture,
-        focus_dist,
-    );
+    // Traces sampled wavelengths instead of RGB, needed for dispersion
+    let spectral = false;
+    // e.g. Exposure::Manual { iso: 100.0, compensation: 0.0 }, with a Camera::physical
+    // and .with_shutter_time(1.0 / 125.0)
+    let exposure = Exposure::Unscaled;
+
+    // Only renders the pixels in x0..x1 and y0..y1, counted from the top left of the full
+    // image. Pixels keep the same (u, v) and sampling as in a full render. Auto exposure
+    // needs the whole image, so it can't be used with a crop.
+    let crop: Option<(i32, i32, i32, i32)> = None;
+    // Earlier full size render to paste the crop into, which needs a crop set. None writes
+    // just the cropped pixels.
+    let composite: Option<&str> = None;
 
     // World generation
     let mut world = HittableList::new();
@@ -129,14 +273,154 @@ fn main() -> Result<(), Error> {
         }
     }
 
-    for i in (0..image_height).rev() {
+    let mut scene = Scene::new(world);
+
+    // Lights, e.g. Light::point(Vec3::new(10.0, 5.0, 10.0), Color::new(1.0, 1.0, 1.0), 50.0)
+    // or Light::sun(Vec3::new(1.0, 2.0, 0.5), Color::new(1.0, 0.95, 0.9), 2.0, 0.53)
+    // or a softbox, Light::quad(corner, u, v, color, 4.0, false).with_solid_angle_sampling()
+    // or a downlight with a measured beam, light.with_ies(Arc::new(IesProfile::load("a.ies")?))
+    // and warm white at 800 lumens with .with_temperature(2700.0).with_luminous_power(800.0)
+    let lights: Vec<Light> = vec![];
+
+    // Lights that only shine on some objects, using the ids world.add returns,
+    // e.g. (light, LightLink::Except(vec![ground]))
+    let linked_lights: Vec<(Light, LightLink)> = vec![];
+
+    for light in lights {
+        scene.add_light(light);
+    }
+
+    for (light, link) in linked_lights {
+        scene.add_linked_light(light, link);
+    }
+
+    // Equirectangular .hdr, .pfm or .ppm to light the scene with instead of the gradient sky,
+    // with its rotation in degrees and a brightness multiplier
+    let environment: Option<(&str, f32, f32)> = None;
+
+    // Or a daylight sky as (sun elevation, sun azimuth, turbidity, ground albedo), which is
+    // far brighter than the gradient so wants e.g. Exposure::Auto { compensation: 0.0 }
+    let sky: Option<(f32, f32, f32, Color)> = None;
+
+    if let Some((path, rotation, intensity)) = environment {
+        scene.background = Background::environment(Image::load(path)?, rotation, intensity);
+    } else if let Some((elevation, azimuth, turbidity, ground_albedo)) = sky {
+        scene.background = Background::sky(elevation, azimuth, turbidity, ground_albedo);
+    }
+
+    if let Some(sun) = scene.background.sun_light() {
+        scene.add_light(sun);
+    }
+
+    // Smoke or clouds from a Mitsuba .vol voxel grid as (file, density scale, center, size),
+    // e.g. ("smoke.vol", 5.0, Vec3::new(12.0, 2.0, 12.0), Vec3::new(4.0, 4.0, 4.0)). Fire
+    // wants .with_temperature(VoxelGrid::load_channel("fire.vol", 1)?, 1.0, 50.0) on it too.
+    let volume: Option<(&str, f32, Vec3, Vec3)> = None;
+
+    if let Some((path, density_scale, center, size)) = volume {
+        let medium = GridMedium::new(
+            VoxelGrid::load(path)?,
+            density_scale,
+            Material::henyey_greenstein(Color::new(0.9, 0.9, 0.9), 0.5),
+        );
+        scene.world.add(Hittable::GridMedium(
+            medium.with_transform(center, size, 0.0),
+        ));
+    }
+
+    // Fog through the whole scene as (density, g), where g from -1 to 1 is how much it
+    // scatters light forwards. Smoke in just one place can go in the world instead, e.g.
+    // Hittable::constant_medium(Hittable::sphere(c, 2.0, m), 0.5, Material::isotropic(white))
+    let fog: Option<(f32, f32)> = None;
+
+    if let Some((density, g)) = fog {
+        scene.set_fog(
+            density,
+            Material::henyey_greenstein(Color::new(1.0, 1.0, 1.0), g),
+        );
+    }
+
+    // All samples every light at every hit. Scenes with lots of lights want Power, or Tree
+    // to favour the lights closest to each point.
+    scene.set_light_sampling(LightSampling::All);
+
+    // Camera
+    let look_from = Vec3::new(15.0, 6.0, 15.0);
+    let look_at = Vec3::new(12.0, 1.0, 12.0);
+    let up_vector = Vec3::new(0.0, 1.0, 0.0);
+
+    let focus_dist = (look_from - look_at).length();
+    let aperture = 0.5;
+    // Point on the image to focus on, or None to focus at look_at
+    let autofocus: Option<(f32, f32)> = None;
+
+    let camera = Camera::new(
+        16.0 / 9.0,
+        look_from,
+        look_at,
+        up_vector,
+        80.0,
+        aperture,
+        focus_dist,
+    );
+
+    let camera = match autofocus {
+        Some((s, t)) => camera.with_autofocus_at(&scene.world, s, t),
+        None => camera,
+    };
+
+    exposure.check(&camera, crop.is_some())?;
+
+    let stereo_camera = stereo.map(|(interpupillary_distance, convergence, layout)| {
+        StereoCamera::new(camera.clone(), interpupillary_distance, convergence, layout)
+    });
+
+    let (x0, y0, x1, y1) = match crop {
+        Some((x0, y0, x1, y1)) => (
+            x0.clamp(0, image_width),
+            y0.clamp(0, image_height),
+            x1.clamp(0, image_width),
+            y1.clamp(0, image_height),
+        ),
+        None => (0, 0, image_width, image_height),
+    };
+    let (crop_width, crop_height) = ((x1 - x0).max(0), (y1 - y0).max(0));
+
+    // Read up front so a bad file fails before rendering, and before the output
+    // overwrites it since they may well be the same file
+    let background = match composite {
+        Some(path) if crop.is_some() => {
+            let image = Image::load_ppm(path)?;
+
+            if image.width != image_width as usize || image.height != image_height as usize {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "composite image doesn't match the render size",
+                ));
+            }
+
+            Some(image)
+        }
+        Some(_) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "a composite image needs a crop to paste into it",
+            ))
+        }
+        None => None,
+    };
+
+    let mut pixels = Vec::with_capacity((crop_width * crop_height) as usize);
+
+    // Rows are numbered from the bottom of the image here, matching v
+    for i in (image_height - y1..image_height - y0).rev() {
         print!(
             "\r{}% Finished rendering",
-            100 - ((i as f32 / image_height as f32) * 100.0) as i32
+            100 - (((i - (image_height - y1)) as f32 / crop_height as f32) * 100.0) as i32
         );
         stdout().flush().unwrap();
 
-        for j in 0..image_width {
+        for j in x0..x1 {
             // let mut sum = Color::new(0.0, 0.0, 0.0);
 
             let j = j as f32;
@@ -158,8 +442,15 @@ fn main() -> Result<(), Error> {
                 let u = (j + rand()) / image_width;
                 let v = (i + rand()) / image_height;
 
-                let ray = camera.get_ray(u, v);
-                *p = Some(ray_color(&ray, &world, depth));
+                let sample = match &stereo_camera {
+                    Some(stereo_camera) => stereo_camera.get_ray(u, v),
+                    None => camera.get_ray(u, v),
+                };
+
+                *p = Some(match sample {
+                    Some((ray, weight)) => sample_color(ray, &scene, depth, spectral) * weight,
+                    None => Color::new(0.0, 0.0, 0.0),
+                });
             });
 
             let mut sum = Vec3::new(0.0, 0.0, 0.0);
@@ -168,7 +459,44 @@ fn main() -> Result<(), Error> {
                 sum += color.unwrap();
             }
 
-            write_color(&mut writer, sum, SAMPLES_PER_PIXEL)?;
+            pixels.push(sum);
+        }
+    }
+
+    // Exposure can depend on the whole image, so it's only applied once everything is rendered
+    let averages: Vec<Color> = pixels
+        .iter()
+        .map(|sum| *sum / SAMPLES_PER_PIXEL as f32)
+        .collect();
+    let scale = exposure.scale(&camera, &averages);
+
+    // File formatting
+    let output = File::create("output.ppm")?;
+    let mut writer = BufWriter::new(output);
+
+    match background {
+        Some(image) => {
+            let image_format = format!("P3\n{} {}\n255", image_width, image_height);
+            writeln!(writer, "{image_format}")?;
+
+            for y in 0..image_height {
+                for x in 0..image_width {
+                    if (x0..x1).contains(&x) && (y0..y1).contains(&y) {
+                        let sum = pixels[((y - y0) * crop_width + x - x0) as usize];
+                        write_color(&mut writer, sum * scale, SAMPLES_PER_PIXEL)?;
+                    } else {
+                        write_color(&mut writer, image.get(x as usize, y as usize), 1)?;
+                    }
+                }
+            }
+        }
+        None => {
+            let image_format = format!("P3\n{} {}\n255", crop_width, crop_height);
+            writeln!(writer, "{image_format}")?;
+
+            for sum in pixels {
+                write_color(&mut writer, sum * scale, SAMPLES_PER_PIXEL)?;
+            }
         }
     }
 
@@ -1,18 +1,47 @@
+mod bumped;
+mod cutout;
 mod dialectric;
+mod diffuse_light;
 mod lambertian;
+mod layered;
 mod metal;
+mod mix;
+mod phase_function;
+mod thin_film;
 
+pub use bumped::NormalMap;
+pub use dialectric::Ior;
+pub use diffuse_light::DiffuseLight;
+pub use thin_film::ThinFilm;
+
+use bumped::Bumped;
+use cutout::Cutout;
 use dialectric::Dialectric;
 use lambertian::Lambertian;
+use layered::Layered;
 use metal::Metal;
+use mix::Mix;
+use phase_function::PhaseFunction;
 
-use crate::{hittable::hit_record::HitRecord, ray::Ray, vec3::Color};
+use crate::{
+    hittable::hit_record::HitRecord,
+    ray::Ray,
+    spectrum::{Blackbody, SampledSpectrum},
+    texture::Texture,
+    vec3::{Color, Vec3},
+};
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum Material {
     Lambertian(Lambertian),
     Metal(Metal),
     Dialectric(Dialectric),
+    Mix(Mix),
+    Layered(Layered),
+    Bumped(Bumped),
+    Cutout(Cutout),
+    DiffuseLight(DiffuseLight),
+    PhaseFunction(PhaseFunction),
 }
 
 impl Material {
@@ -21,11 +50,181 @@ impl Material {
     }
 
     pub fn metal(color: Color, fuzz: f32) -> Material {
-        Material::Metal(Metal::new(color, fuzz))
+        Material::Metal(Metal::new(color, fuzz, None))
+    }
+
+    // A metal coated in a thin film, like heat tinted steel or an oil slick
+    pub fn iridescent_metal(color: Color, fuzz: f32, film: ThinFilm) -> Material {
+        Material::Metal(Metal::new(color, fuzz, Some(film)))
     }
 
     pub fn dialectric(refraction: f32) -> Material {
-        Material::Dialectric(Dialectric::new(refraction))
+        Material::Dialectric(Dialectric::new(Ior::Constant(refraction), None))
+    }
+
+    // A dialectric whose index of refraction varies with wavelength.
+    // Only shows dispersion when rendering in spectral mode.
+    pub fn dispersive(refraction: Ior) -> Material {
+        Material::Dialectric(Dialectric::new(refraction, None))
+    }
+
+    // A dialectric coated in a thin film, like a soap bubble or a coated lens
+    pub fn iridescent_dialectric(refraction: Ior, film: ThinFilm) -> Material {
+        Material::Dialectric(Dialectric::new(refraction, Some(film)))
+    }
+
+    // Blends between a and b, where a weight of 0 is all a and 1 is all b
+    pub fn mix(a: Material, b: Material, weight: f32) -> Material {
+        Material::mix_by_texture(a, b, Texture::solid(Color::new(weight, weight, weight)))
+    }
+
+    pub fn mix_by_texture(a: Material, b: Material, weight: Texture) -> Material {
+        Material::Mix(Mix::new(a, b, weight))
+    }
+
+    // Puts a clear coat with the given index of refraction over the base material
+    pub fn layered(base: Material, refraction: f32, coat_color: Color) -> Material {
+        Material::Layered(Layered::new(base, refraction, coat_color))
+    }
+
+    // Tilts the shading normal using a tangent space normal map
+    pub fn normal_mapped(base: Material, normal_map: Texture) -> Material {
+        Material::Bumped(Bumped::new(base, NormalMap::TangentSpace(normal_map)))
+    }
+
+    // Tilts the shading normal as if the surface was raised by the height map
+    pub fn bump_mapped(base: Material, height_map: Texture, strength: f32) -> Material {
+        Material::Bumped(Bumped::new(
+            base,
+            NormalMap::Height {
+                texture: height_map,
+                strength,
+            },
+        ))
+    }
+
+    // Lets rays pass through wherever the opacity texture is dark
+    pub fn cutout(base: Material, opacity: Texture) -> Material {
+        Material::Cutout(Cutout::new(base, opacity))
+    }
+
+    // Glows with the given color on the front of the surface, without scattering anything
+    pub fn diffuse_light(emission: Color) -> Material {
+        Material::DiffuseLight(DiffuseLight::new(emission, false, false, None))
+    }
+
+    // Glows like a blackbody at `kelvin` with the given luminance in cd/m²
+    pub fn blackbody_light(kelvin: f32, luminance: f32) -> Material {
+        let blackbody = Blackbody::new(kelvin);
+        Material::DiffuseLight(DiffuseLight::new(
+            blackbody.color() * luminance,
+            false,
+            false,
+            Some(blackbody),
+        ))
+    }
+
+    // Scatters the same amount in every direction, for the inside of a medium
+    pub fn isotropic(albedo: Color) -> Material {
+        Material::PhaseFunction(PhaseFunction::new(albedo, 0.0))
+    }
+
+    // Scatters mostly forwards for positive `g` and mostly backwards for negative,
+    // for the inside of a medium. Fog and clouds are around 0.8.
+    pub fn henyey_greenstein(albedo: Color, g: f32) -> Material {
+        Material::PhaseFunction(PhaseFunction::new(albedo, g))
+    }
+
+    // Applies any normal or bump maps to the hit, and settles which lobe of a Mix the
+    // hit uses. Has to happen before scattering.
+    pub fn perturb_normal(&self, record: &mut HitRecord) {
+        match self {
+            Material::Mix(material) => material.perturb_normal(record),
+            Material::Layered(material) => material.perturb_normal(record),
+            Material::Bumped(material) => material.perturb_normal(record),
+            Material::Cutout(material) => material.perturb_normal(record),
+            _ => {}
+        }
+    }
+
+    // Whether a ray hitting this material at (u, v) should stop there, or carry on
+    // through a transparent part of a cutout. Random for partially transparent parts.
+    pub fn alpha_test(&self, u: f32, v: f32, point: Vec3, lobe_sample: f32) -> bool {
+        match self {
+            Material::Mix(material) => material.alpha_test(u, v, point, lobe_sample),
+            Material::Layered(material) => material.alpha_test(u, v, point, lobe_sample),
+            Material::Bumped(material) => material.alpha_test(u, v, point, lobe_sample),
+            Material::Cutout(material) => material.alpha_test(u, v, point, lobe_sample),
+            _ => true,
+        }
+    }
+
+    // Whether scattering off this material splits light up by wavelength
+    pub fn is_wavelength_dependent(&self) -> bool {
+        match self {
+            Material::Lambertian(_) => false,
+            Material::Metal(_) => false,
+            Material::Dialectric(material) => material.is_wavelength_dependent(),
+            // perturb_normal() has already swapped a Mix for the lobe it picked, which gets
+            // asked instead
+            Material::Mix(_) => false,
+            Material::Layered(material) => material.is_wavelength_dependent(),
+            Material::Bumped(material) => material.is_wavelength_dependent(),
+            Material::Cutout(material) => material.is_wavelength_dependent(),
+            Material::DiffuseLight(_) => false,
+            Material::PhaseFunction(_) => false,
+        }
+    }
+
+    // How much light arriving from `direction` gets scattered back along the ray, per unit
+    // solid angle and before the cosine term. Only covers the parts of the material that
+    // can be lit by sampling a light. Mirror-like parts can't, so they come out black.
+    pub fn bsdf(&self, ray: &Ray, record: &HitRecord, direction: Vec3) -> Color {
+        match self {
+            Material::Lambertian(material) => material.bsdf(),
+            Material::Metal(_) => Color::new(0.0, 0.0, 0.0),
+            Material::Dialectric(_) => Color::new(0.0, 0.0, 0.0),
+            Material::Mix(material) => material.bsdf(ray, record, direction),
+            Material::Layered(material) => material.bsdf(ray, record, direction),
+            Material::Bumped(material) => material.bsdf(ray, record, direction),
+            Material::Cutout(material) => material.bsdf(ray, record, direction),
+            Material::DiffuseLight(_) => Color::new(0.0, 0.0, 0.0),
+            Material::PhaseFunction(material) => material.bsdf(ray, direction),
+        }
+    }
+
+    // Light given off by the surface towards where the ray came from
+    pub fn emitted(&self, ray: &Ray, record: &HitRecord) -> Color {
+        match self {
+            Material::Bumped(material) => material.emitted(ray, record),
+            Material::Cutout(material) => material.emitted(ray, record),
+            Material::DiffuseLight(material) => material.emitted(ray, record),
+            Material::PhaseFunction(material) => material.emitted(),
+            _ => Color::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    // The spectrum of what emitted() gives off, for blackbody emitters
+    pub fn blackbody(&self) -> Option<Blackbody> {
+        match self {
+            Material::Bumped(material) => material.blackbody(),
+            Material::Cutout(material) => material.blackbody(),
+            Material::DiffuseLight(material) => material.blackbody(),
+            Material::PhaseFunction(material) => material.blackbody(),
+            _ => None,
+        }
+    }
+
+    // Reflectance that varies with wavelength more finely than RGB can hold, which scatter()'s
+    // color gets multiplied by for spectral rays
+    pub fn spectral_reflectance(&self, ray: &Ray, record: &HitRecord) -> Option<SampledSpectrum> {
+        match self {
+            Material::Metal(material) => material.spectral_reflectance(ray, record),
+            Material::Layered(material) => material.spectral_reflectance(ray, record),
+            Material::Bumped(material) => material.spectral_reflectance(ray, record),
+            Material::Cutout(material) => material.spectral_reflectance(ray, record),
+            _ => None,
+        }
     }
 
     // Scatter returns if the light was reflected as the first parameter
@@ -35,6 +234,12 @@ impl Material {
             Material::Lambertian(material) => material.scatter(ray, record),
             Material::Metal(material) => material.scatter(ray, record),
             Material::Dialectric(material) => material.scatter(ray, record),
+            Material::Mix(material) => material.scatter(ray, record),
+            Material::Layered(material) => material.scatter(ray, record),
+            Material::Bumped(material) => material.scatter(ray, record),
+            Material::Cutout(material) => material.scatter(ray, record),
+            Material::DiffuseLight(material) => material.scatter(ray, record),
+            Material::PhaseFunction(material) => material.scatter(ray, record),
         }
     }
 }
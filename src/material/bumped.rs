@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use super::Material;
+use crate::{
+    hittable::hit_record::HitRecord,
+    ray::Ray,
+    spectrum::{Blackbody, SampledSpectrum},
+    texture::Texture,
+    vec3::{Color, Vec3},
+};
+
+// Step in uv used to estimate the slope of height maps
+const HEIGHT_DELTA: f32 = 1.0 / 1024.0;
+
+#[derive(Clone)]
+pub enum NormalMap {
+    // Colors encode a normal relative to the surface, with (0.5, 0.5, 1.0) pointing straight out
+    TangentSpace(Texture),
+    // Brighter parts of the texture are treated as raised, scaled by strength
+    Height { texture: Texture, strength: f32 },
+}
+
+// Tilts the shading normal of the base material with a normal or height map
+#[derive(Clone)]
+pub struct Bumped {
+    base: Arc<Material>,
+    map: NormalMap,
+}
+
+impl Bumped {
+    pub fn new(base: Material, map: NormalMap) -> Self {
+        Bumped {
+            base: Arc::new(base),
+            map,
+        }
+    }
+
+    pub fn is_wavelength_dependent(&self) -> bool {
+        self.base.is_wavelength_dependent()
+    }
+
+    pub fn alpha_test(&self, u: f32, v: f32, point: Vec3, lobe_sample: f32) -> bool {
+        self.base.alpha_test(u, v, point, lobe_sample)
+    }
+
+    pub fn perturb_normal(&self, record: &mut HitRecord) {
+        let (u, v, point) = (record.u, record.v, record.point);
+        let normal = record.normal;
+
+        // Rebuild the tangent frame around the shading normal, keeping its handedness
+        // so back faces see the map the same way around as front faces
+        let tangent = (record.tangent - normal * normal.dot(&record.tangent)).unit();
+        let handedness = normal.cross(&tangent).dot(&record.bitangent).signum();
+        let bitangent = normal.cross(&tangent) * handedness;
+
+        let perturbed = match &self.map {
+            NormalMap::TangentSpace(texture) => {
+                let n = texture.value(u, v, point) * 2.0 - 1.0;
+                tangent * n.x + bitangent * n.y + normal * n.z
+            }
+            NormalMap::Height { texture, strength } => {
+                let height = texture.scalar(u, v, point);
+                let du = (texture.scalar(u + HEIGHT_DELTA, v, point) - height) / HEIGHT_DELTA;
+                let dv = (texture.scalar(u, v + HEIGHT_DELTA, point) - height) / HEIGHT_DELTA;
+                normal - (tangent * du + bitangent * dv) * *strength
+            }
+        };
+
+        // A map can't turn the normal away from the side of the surface the ray hit
+        if !perturbed.near_zero() && perturbed.dot(&record.geometric_normal) > 0.0 {
+            record.normal = perturbed.unit();
+        }
+
+        self.base.perturb_normal(record);
+    }
+
+    pub fn emitted(&self, ray: &Ray, record: &HitRecord) -> Color {
+        self.base.emitted(ray, record)
+    }
+
+    pub fn blackbody(&self) -> Option<Blackbody> {
+        self.base.blackbody()
+    }
+
+    pub fn spectral_reflectance(&self, ray: &Ray, record: &HitRecord) -> Option<SampledSpectrum> {
+        self.base.spectral_reflectance(ray, record)
+    }
+
+    pub fn bsdf(&self, ray: &Ray, record: &HitRecord, direction: Vec3) -> Color {
+        self.base.bsdf(ray, record, direction)
+    }
+
+    pub fn scatter(&self, ray: &Ray, record: &HitRecord) -> (bool, Ray, Color) {
+        self.base.scatter(ray, record)
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use super::Material;
+use crate::{
+    hittable::hit_record::HitRecord,
+    ray::Ray,
+    spectrum::{Blackbody, SampledSpectrum},
+    texture::Texture,
+    vec3::{Color, Vec3},
+};
+
+// Makes parts of the base material see-through according to an opacity texture,
+// for things like leaves and fences modelled as flat quads
+#[derive(Clone)]
+pub struct Cutout {
+    base: Arc<Material>,
+    opacity: Texture,
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+impl Cutout {
+    pub fn new(base: Material, opacity: Texture) -> Self {
+        Cutout {
+            base: Arc::new(base),
+            opacity,
+        }
+    }
+
+    pub fn is_wavelength_dependent(&self) -> bool {
+        self.base.is_wavelength_dependent()
+    }
+
+    // Partially transparent texels let a matching fraction of rays through
+    pub fn alpha_test(&self, u: f32, v: f32, point: Vec3, lobe_sample: f32) -> bool {
+        let alpha = self.opacity.scalar(u, v, point);
+
+        if alpha >= 1.0 {
+            return self.base.alpha_test(u, v, point, lobe_sample);
+        }
+
+        alpha > 0.0 && rand() < alpha && self.base.alpha_test(u, v, point, lobe_sample)
+    }
+
+    pub fn perturb_normal(&self, record: &mut HitRecord) {
+        self.base.perturb_normal(record);
+    }
+
+    pub fn emitted(&self, ray: &Ray, record: &HitRecord) -> Color {
+        self.base.emitted(ray, record)
+    }
+
+    pub fn blackbody(&self) -> Option<Blackbody> {
+        self.base.blackbody()
+    }
+
+    pub fn spectral_reflectance(&self, ray: &Ray, record: &HitRecord) -> Option<SampledSpectrum> {
+        self.base.spectral_reflectance(ray, record)
+    }
+
+    pub fn bsdf(&self, ray: &Ray, record: &HitRecord, direction: Vec3) -> Color {
+        self.base.bsdf(ray, record, direction)
+    }
+
+    pub fn scatter(&self, ray: &Ray, record: &HitRecord) -> (bool, Ray, Color) {
+        self.base.scatter(ray, record)
+    }
+}
@@ -1,14 +1,58 @@
 use rand::Rng;
 
+use super::ThinFilm;
 use crate::{
     hittable::hit_record::HitRecord,
     ray::Ray,
     vec3::{Color, Vec3},
 };
 
+// Wavelength (nm) of the Fraunhofer d line, used when no wavelength is being traced
+const REFERENCE_WAVELENGTH: f32 = 587.6;
+
+#[derive(Clone, Copy)]
+pub enum Ior {
+    Constant(f32),
+    // n = a + b / λ², with λ in micrometres
+    Cauchy { a: f32, b: f32 },
+    // n² = 1 + Σ bᵢλ² / (λ² - cᵢ), with λ in micrometres
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+impl Ior {
+    pub fn crown_glass() -> Ior {
+        Ior::Sellmeier {
+            b: [1.039_612, 0.231_792_34, 1.010_469_5],
+            c: [0.006_000_699, 0.020_017_914, 103.560_65],
+        }
+    }
+
+    pub fn at(&self, wavelength: f32) -> f32 {
+        let micrometres = wavelength / 1000.0;
+        let l2 = micrometres * micrometres;
+
+        match *self {
+            Ior::Constant(n) => n,
+            Ior::Cauchy { a, b } => a + b / l2,
+            Ior::Sellmeier { b, c } => {
+                let mut n2 = 1.0;
+                for i in 0..3 {
+                    n2 += b[i] * l2 / (l2 - c[i]);
+                }
+                n2.sqrt()
+            }
+        }
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        !matches!(self, Ior::Constant(_))
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Dialectric {
-    refraction: f32,
+    refraction: Ior,
+    film: Option<ThinFilm>,
 }
 
 fn rand() -> f32 {
@@ -16,11 +60,15 @@ fn rand() -> f32 {
 }
 
 impl Dialectric {
-    pub fn new(refraction: f32) -> Self {
-        Dialectric { refraction }
+    pub fn new(refraction: Ior, film: Option<ThinFilm>) -> Self {
+        Dialectric { refraction, film }
     }
 
-    fn reflectance(cosine: f32, refraction: f32) -> f32 {
+    pub fn is_wavelength_dependent(&self) -> bool {
+        self.refraction.is_dispersive() || self.film.is_some()
+    }
+
+    pub fn reflectance(cosine: f32, refraction: f32) -> f32 {
         let mut r0 = (1.0 - refraction) / (1.0 + refraction);
         r0 *= r0;
         r0 + (1.0 - r0) * ((1.0 - cosine).powi(5))
@@ -28,9 +76,13 @@ impl Dialectric {
 
     pub fn scatter(&self, ray: &Ray, record: &HitRecord) -> (bool, Ray, Color) {
         let attenuation = Color::new(1.0, 1.0, 1.0);
+        let wavelength = ray
+            .wavelengths
+            .map_or(REFERENCE_WAVELENGTH, |wavelengths| wavelengths.hero());
+        let refraction = self.refraction.at(wavelength);
         let refraction_ratio = match record.front_face {
-            true => 1.0 / self.refraction,
-            false => self.refraction,
+            true => 1.0 / refraction,
+            false => refraction,
         };
 
         let unit_direction = ray.direction.unit();
@@ -38,16 +90,58 @@ impl Dialectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction: Vec3;
 
-        if cannot_refract || Dialectric::reflectance(cos_theta, refraction_ratio) > rand() {
-            direction = Vec3::reflect(unit_direction, record.normal);
-        } else {
-            direction = Vec3::refract(unit_direction, record.normal, refraction_ratio);
+        if let Some(film) = self.film {
+            return self.scatter_through_film(&film, ray, record, refraction, cannot_refract);
         }
 
+        let direction =
+            if cannot_refract || Dialectric::reflectance(cos_theta, refraction_ratio) > rand() {
+                Vec3::reflect(unit_direction, record.normal)
+            } else {
+                Vec3::refract(unit_direction, record.normal, refraction_ratio)
+            };
+
         let scattered = Ray::new(record.point, direction);
 
         (true, scattered, attenuation)
     }
+
+    // The film makes the reflectance a color rather than a single probability, so pick
+    // between reflecting and refracting by its average and reweight by the actual color
+    fn scatter_through_film(
+        &self,
+        film: &ThinFilm,
+        ray: &Ray,
+        record: &HitRecord,
+        refraction: f32,
+        cannot_refract: bool,
+    ) -> (bool, Ray, Color) {
+        let unit_direction = ray.direction.unit();
+        let reflected = Ray::new(record.point, Vec3::reflect(unit_direction, record.normal));
+
+        if cannot_refract {
+            return (true, reflected, Color::new(1.0, 1.0, 1.0));
+        }
+
+        let (outside, base) = match record.front_face {
+            true => (1.0, refraction),
+            false => (refraction, 1.0),
+        };
+        let cos_theta = ((-unit_direction).dot(&record.normal)).min(1.0);
+        let reflectance = film.over_dialectric(ray, cos_theta, outside, base);
+        let probability = ((reflectance.x + reflectance.y + reflectance.z) / 3.0).clamp(0.01, 0.99);
+
+        if rand() < probability {
+            return (true, reflected, reflectance / probability);
+        }
+
+        let transmittance = Color::new(1.0, 1.0, 1.0) - reflectance;
+        let refracted = Ray::new(
+            record.point,
+            Vec3::refract(unit_direction, record.normal, outside / base),
+        );
+
+        (true, refracted, transmittance / (1.0 - probability))
+    }
 }
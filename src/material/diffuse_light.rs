@@ -0,0 +1,50 @@
+use crate::{hittable::hit_record::HitRecord, ray::Ray, spectrum::Blackbody, vec3::Color};
+
+// A surface that gives off light instead of scattering it
+#[derive(Clone, Copy)]
+pub struct DiffuseLight {
+    emission: Color,
+    // One sided lights only shine out of their front face
+    two_sided: bool,
+    // Whether the surface belongs to one of the scene's lights, so direct lighting
+    // already accounts for it
+    sampled: bool,
+    // Set for blackbody emitters, so spectral renders can use the real spectrum
+    blackbody: Option<Blackbody>,
+}
+
+impl DiffuseLight {
+    pub fn new(
+        emission: Color,
+        two_sided: bool,
+        sampled: bool,
+        blackbody: Option<Blackbody>,
+    ) -> Self {
+        DiffuseLight {
+            emission,
+            two_sided,
+            sampled,
+            blackbody,
+        }
+    }
+
+    pub fn blackbody(&self) -> Option<Blackbody> {
+        self.blackbody
+    }
+
+    pub fn emitted(&self, ray: &Ray, record: &HitRecord) -> Color {
+        if (self.sampled && ray.from_diffuse) || !(record.front_face || self.two_sided) {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        self.emission
+    }
+
+    pub fn scatter(&self, ray: &Ray, record: &HitRecord) -> (bool, Ray, Color) {
+        (
+            false,
+            Ray::new(record.point, ray.direction),
+            Color::new(0.0, 0.0, 0.0),
+        )
+    }
+}
@@ -1,3 +1,5 @@
+use std::f32::consts::PI;
+
 use crate::{
     hittable::hit_record::HitRecord,
     ray::Ray,
@@ -14,14 +16,19 @@ impl Lambertian {
         Lambertian { color }
     }
 
-    pub fn scatter(&self, ray: &Ray, record: &HitRecord) -> (bool, Ray, Color) {
+    pub fn bsdf(&self) -> Color {
+        self.color / PI
+    }
+
+    pub fn scatter(&self, _ray: &Ray, record: &HitRecord) -> (bool, Ray, Color) {
         let mut scatter_direction = record.normal + Vec3::random_unit_vec();
 
         if scatter_direction.near_zero() {
             scatter_direction = record.normal;
         }
 
-        let scattered = Ray::new(record.point, scatter_direction);
+        let mut scattered = Ray::new(record.point, scatter_direction);
+        scattered.from_diffuse = true;
         let attenuation = self.color;
         (true, scattered, attenuation)
     }
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use super::{dialectric::Dialectric, Material};
+use crate::{
+    hittable::hit_record::HitRecord,
+    ray::Ray,
+    spectrum::SampledSpectrum,
+    vec3::{Color, Vec3},
+};
+
+// A clear dialectric coat (varnish, lacquer, clearcoat paint) over any other material.
+// Light either reflects off the coat or passes through it to the base, and whatever
+// the base sends back loses the part the coat reflects back inwards on the way out.
+#[derive(Clone)]
+pub struct Layered {
+    base: Arc<Material>,
+    refraction: f32,
+    // Tint picked up by light crossing the coat
+    coat_color: Color,
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+impl Layered {
+    pub fn new(base: Material, refraction: f32, coat_color: Color) -> Self {
+        Layered {
+            base: Arc::new(base),
+            refraction,
+            coat_color,
+        }
+    }
+
+    pub fn alpha_test(&self, u: f32, v: f32, point: Vec3, lobe_sample: f32) -> bool {
+        self.base.alpha_test(u, v, point, lobe_sample)
+    }
+
+    // The coat follows whatever normal map the base has. A Mix in the base swaps itself for
+    // the lobe it picked, so the coat goes back on over that lobe.
+    pub fn perturb_normal(&self, record: &mut HitRecord) {
+        let material = record.material.clone();
+        self.base.perturb_normal(record);
+
+        if !Arc::ptr_eq(&record.material, &material) {
+            record.material = Arc::new(Material::Layered(Layered {
+                base: record.material.clone(),
+                ..self.clone()
+            }));
+        }
+    }
+
+    pub fn is_wavelength_dependent(&self) -> bool {
+        self.base.is_wavelength_dependent()
+    }
+
+    // Only what passes through the coat reaches the base, and the coat itself is the same
+    // at every wavelength
+    pub fn spectral_reflectance(&self, ray: &Ray, record: &HitRecord) -> Option<SampledSpectrum> {
+        self.base.spectral_reflectance(ray, record)
+    }
+
+    // Light reaching the base has to get through the coat both on the way in and out
+    pub fn bsdf(&self, ray: &Ray, record: &HitRecord, direction: Vec3) -> Color {
+        let cos_in = (-ray.direction.unit()).dot(&record.normal).clamp(0.0, 1.0);
+        let cos_out = direction.unit().dot(&record.normal).abs().min(1.0);
+        let transmitted = (1.0 - Dialectric::reflectance(cos_in, self.refraction))
+            * (1.0 - Dialectric::reflectance(cos_out, self.refraction));
+
+        self.base.bsdf(ray, record, direction) * self.coat_color * transmitted
+    }
+
+    pub fn scatter(&self, ray: &Ray, record: &HitRecord) -> (bool, Ray, Color) {
+        let unit_direction = ray.direction.unit();
+        let cos_in = (-unit_direction).dot(&record.normal).clamp(0.0, 1.0);
+
+        if Dialectric::reflectance(cos_in, self.refraction) > rand() {
+            let reflected = Ray::new(record.point, Vec3::reflect(unit_direction, record.normal));
+            return (true, reflected, Color::new(1.0, 1.0, 1.0));
+        }
+
+        // A base that lets light through sends it out the far side of the coat instead
+        let (was_scattered, scattered, attenuation) = self.base.scatter(ray, record);
+        let cos_out = scattered
+            .direction
+            .unit()
+            .dot(&record.normal)
+            .abs()
+            .min(1.0);
+        let transmitted = 1.0 - Dialectric::reflectance(cos_out, self.refraction);
+
+        (
+            was_scattered,
+            scattered,
+            attenuation * self.coat_color * transmitted,
+        )
+    }
+}
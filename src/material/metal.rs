@@ -1,6 +1,8 @@
+use super::ThinFilm;
 use crate::{
     hittable::hit_record::HitRecord,
     ray::Ray,
+    spectrum::SampledSpectrum,
     vec3::{Color, Vec3},
 };
 
@@ -8,11 +10,21 @@ use crate::{
 pub struct Metal {
     color: Color,
     fuzz: f32,
+    film: Option<ThinFilm>,
 }
 
 impl Metal {
-    pub fn new(color: Color, fuzz: f32) -> Self {
-        Metal { color, fuzz }
+    pub fn new(color: Color, fuzz: f32, film: Option<ThinFilm>) -> Self {
+        Metal { color, fuzz, film }
+    }
+
+    // Spectral rays get the film's color from spectral_reflectance() instead
+    pub fn spectral_reflectance(&self, ray: &Ray, record: &HitRecord) -> Option<SampledSpectrum> {
+        let film = self.film?;
+        let wavelengths = ray.wavelengths?;
+        let cos_theta = (-ray.direction.unit()).dot(&record.normal).clamp(0.0, 1.0);
+
+        Some(film.over_metal_spectrum(cos_theta, self.color, &wavelengths))
     }
 
     pub fn scatter(&self, ray: &Ray, record: &HitRecord) -> (bool, Ray, Color) {
@@ -21,7 +33,14 @@ impl Metal {
             record.point,
             reflected + Vec3::rand_in_unit_sphere() * self.fuzz,
         );
-        let attenuation = self.color;
+        let attenuation = match (self.film, ray.wavelengths) {
+            (Some(_), Some(_)) => Color::new(1.0, 1.0, 1.0),
+            (Some(film), None) => {
+                let cos_theta = (-ray.direction.unit()).dot(&record.normal).clamp(0.0, 1.0);
+                film.over_metal(cos_theta, self.color)
+            }
+            (None, _) => self.color,
+        };
         let was_scattered = scattered.direction.dot(&record.normal) > 0.0;
         (was_scattered, scattered, attenuation)
     }
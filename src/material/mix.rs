@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use super::Material;
+use crate::{
+    hittable::hit_record::HitRecord,
+    ray::Ray,
+    texture::Texture,
+    vec3::{Color, Vec3},
+};
+
+// Blends two materials, picking `b` with a probability given by the weight texture
+#[derive(Clone)]
+pub struct Mix {
+    a: Arc<Material>,
+    b: Arc<Material>,
+    weight: Texture,
+}
+
+impl Mix {
+    pub fn new(a: Material, b: Material, weight: Texture) -> Self {
+        Mix {
+            a: Arc::new(a),
+            b: Arc::new(b),
+            weight,
+        }
+    }
+
+    // The lobe a hit's lobe sample picks, and the sample stretched back over [0, 1) for
+    // any Mix inside that lobe to pick with
+    fn pick(&self, u: f32, v: f32, point: Vec3, lobe_sample: f32) -> (&Arc<Material>, f32) {
+        let weight = self.weight.scalar(u, v, point);
+
+        match lobe_sample < weight {
+            true => (&self.b, lobe_sample / weight),
+            false => (&self.a, (lobe_sample - weight) / (1.0 - weight)),
+        }
+    }
+
+    // Opaque wherever the lobe the hit goes on to use is
+    pub fn alpha_test(&self, u: f32, v: f32, point: Vec3, lobe_sample: f32) -> bool {
+        let (lobe, lobe_sample) = self.pick(u, v, point, lobe_sample);
+        lobe.alpha_test(u, v, point, lobe_sample)
+    }
+
+    // Settles on the lobe the alpha test picked for the rest of the hit, so the normal that
+    // gets perturbed belongs to the lobe that then scatters and is lit
+    pub fn perturb_normal(&self, record: &mut HitRecord) {
+        let (lobe, lobe_sample) = self.pick(record.u, record.v, record.point, record.lobe_sample);
+        let lobe = lobe.clone();
+
+        record.material = lobe.clone();
+        record.lobe_sample = lobe_sample;
+        lobe.perturb_normal(record);
+    }
+
+    // Scattering picks one of the two at random, so on average it's a blend of both
+    pub fn bsdf(&self, ray: &Ray, record: &HitRecord, direction: Vec3) -> Color {
+        let weight = self.weight.scalar(record.u, record.v, record.point);
+        self.a.bsdf(ray, record, direction) * (1.0 - weight)
+            + self.b.bsdf(ray, record, direction) * weight
+    }
+
+    pub fn scatter(&self, ray: &Ray, record: &HitRecord) -> (bool, Ray, Color) {
+        let (lobe, _) = self.pick(record.u, record.v, record.point, record.lobe_sample);
+        lobe.scatter(ray, record)
+    }
+}
@@ -0,0 +1,97 @@
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+use crate::{
+    hittable::hit_record::HitRecord,
+    ray::Ray,
+    spectrum::Blackbody,
+    vec3::{Color, Vec3},
+};
+
+// How light scatters inside a participating medium like fog or smoke. The Henyey-Greenstein
+// `g` goes from -1 for light bouncing straight back to 1 for light carrying on forwards,
+// with 0 scattering equally in every direction.
+#[derive(Clone, Copy)]
+pub struct PhaseFunction {
+    albedo: Color,
+    g: f32,
+    // Light given off where the medium glows, like in fire
+    emission: Color,
+    blackbody: Option<Blackbody>,
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+impl PhaseFunction {
+    pub fn new(albedo: Color, g: f32) -> Self {
+        PhaseFunction {
+            albedo,
+            g: g.clamp(-0.99, 0.99),
+            emission: Color::new(0.0, 0.0, 0.0),
+            blackbody: None,
+        }
+    }
+
+    // Glowing with radiance `emission` where the medium absorbs light, so with
+    // (1 - albedo) of it at each collision
+    pub fn with_emission(self, emission: Color, blackbody: Option<Blackbody>) -> Self {
+        PhaseFunction {
+            emission: (Color::new(1.0, 1.0, 1.0) - self.albedo) * emission,
+            blackbody,
+            ..self
+        }
+    }
+
+    pub fn emitted(&self) -> Color {
+        self.emission
+    }
+
+    pub fn blackbody(&self) -> Option<Blackbody> {
+        self.blackbody
+    }
+
+    // Per unit solid angle, for light turning by an angle with the given cosine
+    fn value(&self, cos_theta: f32) -> f32 {
+        let g = self.g;
+        let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+    }
+
+    pub fn bsdf(&self, ray: &Ray, direction: Vec3) -> Color {
+        self.albedo * self.value(ray.direction.unit().dot(&direction.unit()))
+    }
+
+    // Samples the phase function exactly, so only the albedo is left over
+    pub fn scatter(&self, ray: &Ray, record: &HitRecord) -> (bool, Ray, Color) {
+        let g = self.g;
+        let u = rand();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+            (1.0 + g * g - s * s) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rand();
+
+        let forward = ray.direction.unit();
+        let helper = match forward.x.abs() > 0.9 {
+            true => Vec3::new(0.0, 1.0, 0.0),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let tangent = helper.cross(&forward).unit();
+        let bitangent = forward.cross(&tangent);
+
+        let direction = forward * cos_theta
+            + tangent * (sin_theta * phi.cos())
+            + bitangent * (sin_theta * phi.sin());
+
+        // Lights are sampled directly from inside media too
+        let mut scattered = Ray::new(record.point, direction);
+        scattered.from_diffuse = true;
+        (true, scattered, self.albedo)
+    }
+}
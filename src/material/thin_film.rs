@@ -0,0 +1,135 @@
+use std::f32::consts::PI;
+
+use crate::{
+    ray::Ray,
+    spectrum::{self, SampledSpectrum, SampledWavelengths},
+    vec3::Color,
+};
+
+// Wavelengths (nm) standing in for the red, green and blue channels of RGB renders
+const RGB_WAVELENGTHS: [f32; 3] = [650.0, 532.0, 450.0];
+
+// A thin transparent layer (soap, oil, lens coatings) sitting on top of a surface.
+// Light bouncing between the top and bottom of the film interferes with itself,
+// so the reflectance depends on the wavelength and the viewing angle.
+#[derive(Clone, Copy)]
+pub struct ThinFilm {
+    // In nanometres
+    thickness: f32,
+    ior: f32,
+}
+
+// Fresnel amplitude coefficients for s and p polarised light
+fn amplitudes(n1: f32, n2: f32, cos1: f32, cos2: f32) -> (f32, f32) {
+    let s = (n1 * cos1 - n2 * cos2) / (n1 * cos1 + n2 * cos2);
+    let p = (n2 * cos1 - n1 * cos2) / (n2 * cos1 + n1 * cos2);
+    (s, p)
+}
+
+// Airy summation of every bounce inside the film, for real amplitude coefficients
+fn airy(r12: f32, r23: f32, phase: f32) -> f32 {
+    let cross = 2.0 * r12 * r23 * phase.cos();
+    let numerator = r12 * r12 + r23 * r23 + cross;
+    let denominator = 1.0 + r12 * r12 * r23 * r23 + cross;
+    (numerator / denominator).clamp(0.0, 1.0)
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f32, ior: f32) -> Self {
+        ThinFilm { thickness, ior }
+    }
+
+    // Returns the cosine of the angle inside the film, or None if the light can't enter it
+    fn cos_in_film(&self, cos_theta: f32, outside: f32) -> Option<f32> {
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let sin_film = outside * sin_theta / self.ior;
+
+        if sin_film >= 1.0 {
+            return None;
+        }
+
+        Some((1.0 - sin_film * sin_film).sqrt())
+    }
+
+    fn phase(&self, cos_film: f32, wavelength: f32) -> f32 {
+        4.0 * PI * self.ior * self.thickness * cos_film / wavelength
+    }
+
+    // Reflectance of the film over a dialectric with index `base`, for light arriving from
+    // a medium with index `outside`
+    pub fn reflectance(&self, cos_theta: f32, outside: f32, base: f32, wavelength: f32) -> f32 {
+        let cos_film = match self.cos_in_film(cos_theta, outside) {
+            Some(cos_film) => cos_film,
+            None => return 1.0,
+        };
+
+        let sin_film = (1.0 - cos_film * cos_film).sqrt();
+        let sin_base = self.ior * sin_film / base;
+
+        if sin_base >= 1.0 {
+            return 1.0;
+        }
+
+        let cos_base = (1.0 - sin_base * sin_base).sqrt();
+        let (s12, p12) = amplitudes(outside, self.ior, cos_theta, cos_film);
+        let (s23, p23) = amplitudes(self.ior, base, cos_film, cos_base);
+        let phase = self.phase(cos_film, wavelength);
+
+        0.5 * (airy(s12, s23, phase) + airy(p12, p23, phase))
+    }
+
+    // Reflectance of the film over a metal whose own reflectance is `base`.
+    // Metals are treated as flipping the phase of the light they reflect.
+    pub fn reflectance_over_metal(&self, cos_theta: f32, base: f32, wavelength: f32) -> f32 {
+        let cos_film = match self.cos_in_film(cos_theta, 1.0) {
+            Some(cos_film) => cos_film,
+            None => return 1.0,
+        };
+
+        let (s12, p12) = amplitudes(1.0, self.ior, cos_theta, cos_film);
+        let r23 = -base.clamp(0.0, 1.0).sqrt();
+        let phase = self.phase(cos_film, wavelength);
+
+        0.5 * (airy(s12, r23, phase) + airy(p12, r23, phase))
+    }
+
+    // Spectral rays only follow the hero wavelength, RGB rays get one wavelength per channel
+    pub fn over_dialectric(&self, ray: &Ray, cos_theta: f32, outside: f32, base: f32) -> Color {
+        match ray.wavelengths {
+            Some(wavelengths) => {
+                let r = self.reflectance(cos_theta, outside, base, wavelengths.hero());
+                Color::new(r, r, r)
+            }
+            None => Color::new(
+                self.reflectance(cos_theta, outside, base, RGB_WAVELENGTHS[0]),
+                self.reflectance(cos_theta, outside, base, RGB_WAVELENGTHS[1]),
+                self.reflectance(cos_theta, outside, base, RGB_WAVELENGTHS[2]),
+            ),
+        }
+    }
+
+    pub fn over_metal(&self, cos_theta: f32, base: Color) -> Color {
+        Color::new(
+            self.reflectance_over_metal(cos_theta, base.x, RGB_WAVELENGTHS[0]),
+            self.reflectance_over_metal(cos_theta, base.y, RGB_WAVELENGTHS[1]),
+            self.reflectance_over_metal(cos_theta, base.z, RGB_WAVELENGTHS[2]),
+        )
+    }
+
+    // A metal reflects every wavelength the same way, so spectral rays keep them all
+    pub fn over_metal_spectrum(
+        &self,
+        cos_theta: f32,
+        base: Color,
+        wavelengths: &SampledWavelengths,
+    ) -> SampledSpectrum {
+        let mut values = [0.0; spectrum::SPECTRUM_SAMPLES];
+
+        for (v, lambda) in values.iter_mut().zip(wavelengths.lambda) {
+            let base = spectrum::rgb_to_spectrum(base, lambda);
+            *v = self.reflectance_over_metal(cos_theta, base, lambda);
+        }
+
+        SampledSpectrum { values }
+    }
+}
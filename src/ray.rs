@@ -1,13 +1,39 @@
-use crate::vec3::Vec3;
+use crate::{spectrum::SampledWavelengths, vec3::Vec3};
+
+// What a ray is for, which decides the objects it can see
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RayKind {
+    // Straight from the camera
+    Camera,
+    // Scattered off a surface, so it shows up as reflections and refractions
+    Indirect,
+    // Checking whether anything blocks a light
+    Shadow,
+}
 
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
+    // Only set when rendering in spectral mode
+    pub wavelengths: Option<SampledWavelengths>,
+    // When during the exposure the ray was cast, for motion blur
+    pub time: f32,
+    // Set on rays scattered off a diffuse surface, which has already sampled the lights
+    // directly. Those rays mustn't pick up the same lights' emission a second time.
+    pub from_diffuse: bool,
+    pub kind: RayKind,
 }
 
 impl Ray {
     pub fn new(origin: Vec3, direction: Vec3) -> Self {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            wavelengths: None,
+            time: 0.0,
+            from_diffuse: false,
+            kind: RayKind::Camera,
+        }
     }
 
     pub fn at(&self, t: f32) -> Vec3 {
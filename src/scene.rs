@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::{
+    aabb::Aabb,
+    background::Background,
+    hittable::{hit_record::HitRecord, HittableList},
+    light::{Light, LightLink, LightSampler, LightSampling},
+    material::Material,
+    ray::Ray,
+};
+
+// A medium of constant density filling the scene, out to the bounds of the world.
+// Rays leaving those bounds carry on to the background unaffected.
+pub struct Fog {
+    density: f32,
+    phase: Arc<Material>,
+    bounds: Aabb,
+}
+
+fn rand() -> f32 {
+    rand::thread_rng().gen::<f32>()
+}
+
+impl Fog {
+    // Scatters the ray somewhere short of `t_max` if the fog gets in the way first
+    pub fn hit(&self, ray: &Ray, t_max: f32, hit_record: &mut HitRecord) -> bool {
+        let Some((t0, t1)) = self.bounds.hit_interval(ray, 0.001, t_max) else {
+            return false;
+        };
+
+        let length = ray.direction.length();
+        let distance = -(1.0 - rand()).ln() / self.density;
+
+        if distance > (t1 - t0) * length {
+            return false;
+        }
+
+        hit_record.set_in_medium(ray, t0 + distance / length, self.phase.clone());
+        hit_record.object = None;
+        true
+    }
+
+    // How much light makes it `t_max` along the ray without being scattered away
+    pub fn transmittance(&self, ray: &Ray, t_max: f32) -> f32 {
+        match self.bounds.hit_interval(ray, 0.001, t_max) {
+            Some((t0, t1)) => (-self.density * (t1 - t0) * ray.direction.length()).exp(),
+            None => 1.0,
+        }
+    }
+}
+
+// Everything that gets rendered, apart from the camera looking at it
+pub struct Scene {
+    pub world: HittableList,
+    // Lights sampled directly from every diffuse surface
+    pub lights: Vec<Light>,
+    // Which objects each of the lights shines on
+    light_links: Vec<LightLink>,
+    pub background: Background,
+    pub fog: Option<Fog>,
+    light_sampling: LightSampling,
+    light_sampler: LightSampler,
+}
+
+impl Scene {
+    pub fn new(world: HittableList) -> Self {
+        Scene {
+            world,
+            lights: Vec::new(),
+            light_links: Vec::new(),
+            background: Background::Gradient,
+            fog: None,
+            light_sampling: LightSampling::All,
+            light_sampler: LightSampler::All,
+        }
+    }
+
+    // Area lights are added to the world too, so they can be seen and hit
+    pub fn add_light(&mut self, light: Light) {
+        self.add_linked_light(light, LightLink::All);
+    }
+
+    // A light that only directly lights some of the objects in the world
+    pub fn add_linked_light(&mut self, light: Light, link: LightLink) {
+        if let Some(geometry) = light.geometry() {
+            self.world.add(geometry);
+        }
+
+        self.lights.push(light);
+        self.light_links.push(link);
+        self.update_light_sampler();
+    }
+
+    pub fn light_link(&self, light: usize) -> &LightLink {
+        &self.light_links[light]
+    }
+
+    pub fn set_light_sampling(&mut self, sampling: LightSampling) {
+        self.light_sampling = sampling;
+        self.update_light_sampler();
+    }
+
+    // Fills the world with fog scattering with a phase function material like
+    // Material::isotropic. Uses the bounds of the world as it is now, so wants to come
+    // after everything has been added.
+    pub fn set_fog(&mut self, density: f32, phase: Material) {
+        self.fog = Some(Fog {
+            density,
+            phase: Arc::new(phase),
+            bounds: self.world.bounding_box(),
+        });
+    }
+
+    pub fn light_sampler(&self) -> &LightSampler {
+        &self.light_sampler
+    }
+
+    fn update_light_sampler(&mut self) {
+        let bounds = self.world.bounding_box();
+        let radius = (bounds.max - bounds.min).length() / 2.0;
+        self.light_sampler = LightSampler::new(self.light_sampling, &self.lights, radius);
+    }
+}
@@ -0,0 +1,371 @@
+use std::ops::{Add, AddAssign, Mul};
+
+use crate::{ray::Ray, vec3::Color};
+
+// Visible range covered by the RGB upsampling tables below
+pub const LAMBDA_MIN: f32 = 380.0;
+pub const LAMBDA_MAX: f32 = 720.0;
+
+// Number of wavelengths traced together along a single path
+pub const SPECTRUM_SAMPLES: usize = 4;
+
+// Integral of the CIE y matching function over [LAMBDA_MIN, LAMBDA_MAX]
+const CIE_Y_INTEGRAL: f32 = 106.911_87;
+
+// Scales the film response so a flat spectrum of 1.0 comes out as pure white
+const WHITE_BALANCE: [f32; 3] = [0.832_912_4, 1.053_033_4, 1.101_433];
+
+// Smits' RGB to spectrum basis, 10 bins evenly spread over [LAMBDA_MIN, LAMBDA_MAX]
+const SMITS_WHITE: [f32; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f32; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f32; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f32; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f32; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f32; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f32; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+// Linearly interpolates one of the Smits tables, treating each value as the centre of its bin
+fn smits_at(table: &[f32; 10], lambda: f32) -> f32 {
+    let bin_width = (LAMBDA_MAX - LAMBDA_MIN) / 10.0;
+    let x = ((lambda - LAMBDA_MIN) / bin_width - 0.5).clamp(0.0, 9.0);
+    let i = (x as usize).min(8);
+    let t = x - i as f32;
+    table[i] * (1.0 - t) + table[i + 1] * t
+}
+
+// Evaluates the spectrum Smits' method builds for an RGB triplet at a single wavelength
+pub fn rgb_to_spectrum(color: Color, lambda: f32) -> f32 {
+    let (r, g, b) = (color.x, color.y, color.z);
+    let at = |table| smits_at(table, lambda);
+
+    if r <= g && r <= b {
+        let base = r * at(&SMITS_WHITE);
+        if g <= b {
+            base + (g - r) * at(&SMITS_CYAN) + (b - g) * at(&SMITS_BLUE)
+        } else {
+            base + (b - r) * at(&SMITS_CYAN) + (g - b) * at(&SMITS_GREEN)
+        }
+    } else if g <= r && g <= b {
+        let base = g * at(&SMITS_WHITE);
+        if r <= b {
+            base + (r - g) * at(&SMITS_MAGENTA) + (b - r) * at(&SMITS_BLUE)
+        } else {
+            base + (b - g) * at(&SMITS_MAGENTA) + (r - b) * at(&SMITS_RED)
+        }
+    } else {
+        let base = b * at(&SMITS_WHITE);
+        if r <= g {
+            base + (r - b) * at(&SMITS_YELLOW) + (g - r) * at(&SMITS_GREEN)
+        } else {
+            base + (g - b) * at(&SMITS_YELLOW) + (r - g) * at(&SMITS_RED)
+        }
+    }
+}
+
+// Piecewise gaussian used by the Wyman, Sloan and Shirley fit of the CIE 1931 observer
+fn lobe(lambda: f32, mean: f32, sigma_low: f32, sigma_high: f32) -> f32 {
+    let sigma = if lambda < mean { sigma_low } else { sigma_high };
+    let x = (lambda - mean) / sigma;
+    (-0.5 * x * x).exp()
+}
+
+pub fn cie_xyz(lambda: f32) -> Color {
+    let x = 1.056 * lobe(lambda, 599.8, 37.9, 31.0) + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(lambda, 568.8, 46.9, 40.5) + 0.286 * lobe(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(lambda, 437.0, 11.8, 36.0) + 0.681 * lobe(lambda, 459.0, 26.0, 13.8);
+    Color::new(x, y, z)
+}
+
+// Linear sRGB with a D65 white point
+pub fn xyz_to_srgb(xyz: Color) -> Color {
+    Color::new(
+        3.240_454_2 * xyz.x - 1.537_138_5 * xyz.y - 0.498_531_4 * xyz.z,
+        -0.969_266 * xyz.x + 1.876_010_8 * xyz.y + 0.041_556 * xyz.z,
+        0.055_643_4 * xyz.x - 0.204_025_9 * xyz.y + 1.057_225_2 * xyz.z,
+    )
+}
+
+// Planck's law for a blackbody at `kelvin`, in W/(m²·sr·m) at a wavelength in nm
+fn planck(lambda: f32, kelvin: f32) -> f64 {
+    const H: f64 = 6.626_070_15e-34;
+    const C: f64 = 2.997_924_58e8;
+    const K_B: f64 = 1.380_649e-23;
+
+    let l = lambda as f64 * 1e-9;
+    2.0 * H * C * C / (l.powi(5) * ((H * C / (l * K_B * kelvin as f64)).exp() - 1.0))
+}
+
+// The light given off by an ideal blackbody, scaled so it renders with a luminance of 1.
+// RGB renders use `color`, spectral renders evaluate the Planck curve itself.
+#[derive(Debug, Clone, Copy)]
+pub struct Blackbody {
+    pub kelvin: f32,
+    scale: f64,
+    color: Color,
+}
+
+impl Blackbody {
+    pub fn new(kelvin: f32) -> Self {
+        let kelvin = kelvin.max(1.0);
+
+        // Same conversion the film applies to spectral radiance, integrated in 1nm steps
+        let mut xyz = Color::new(0.0, 0.0, 0.0);
+        let mut lambda = LAMBDA_MIN + 0.5;
+        while lambda < LAMBDA_MAX {
+            xyz += cie_xyz(lambda) * planck(lambda, kelvin) as f32;
+            lambda += 1.0;
+        }
+
+        let rgb = xyz_to_srgb(xyz / CIE_Y_INTEGRAL);
+        let rgb = Color::new(
+            rgb.x * WHITE_BALANCE[0],
+            rgb.y * WHITE_BALANCE[1],
+            rgb.z * WHITE_BALANCE[2],
+        );
+        let luminance = rgb.luminance();
+
+        Blackbody {
+            kelvin,
+            scale: 1.0 / luminance as f64,
+            color: rgb / luminance,
+        }
+    }
+
+    // Linear sRGB with a luminance of 1. Very hot or cold blackbodies lie outside the sRGB
+    // gamut, so a channel can come out slightly negative.
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn value(&self, lambda: f32) -> f32 {
+        (planck(lambda, self.kelvin) * self.scale) as f32
+    }
+
+    // How bright the blackbody really is, before scaling, for comparing temperatures
+    pub fn unscaled_luminance(&self) -> f32 {
+        (1.0 / self.scale) as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SampledWavelengths {
+    pub lambda: [f32; SPECTRUM_SAMPLES],
+    pub pdf: f32,
+    // Set once a wavelength dependent scatter has dropped all but the hero wavelength
+    pub terminated: bool,
+}
+
+impl SampledWavelengths {
+    // Hero wavelength sampling: one uniformly chosen wavelength, plus the others evenly
+    // rotated around the visible range so the whole spectrum is covered by every path
+    pub fn sample(u: f32) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let hero = LAMBDA_MIN + u * range;
+        let mut lambda = [0.0; SPECTRUM_SAMPLES];
+
+        for (i, l) in lambda.iter_mut().enumerate() {
+            let offset = i as f32 / SPECTRUM_SAMPLES as f32 * range;
+            *l = LAMBDA_MIN + (hero - LAMBDA_MIN + offset) % range;
+        }
+
+        SampledWavelengths {
+            lambda,
+            pdf: 1.0 / range,
+            terminated: false,
+        }
+    }
+
+    pub fn hero(&self) -> f32 {
+        self.lambda[0]
+    }
+
+    // Drops the secondary wavelengths. Returns true only the first time, which is when
+    // the path's spectrum needs reweighting to match.
+    pub fn terminate_secondary(&mut self) -> bool {
+        let first = !self.terminated;
+        self.terminated = true;
+        first
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SampledSpectrum {
+    pub values: [f32; SPECTRUM_SAMPLES],
+}
+
+impl SampledSpectrum {
+    pub fn new(value: f32) -> Self {
+        SampledSpectrum {
+            values: [value; SPECTRUM_SAMPLES],
+        }
+    }
+
+    pub fn from_rgb(color: Color, wavelengths: &SampledWavelengths) -> Self {
+        let mut values = [0.0; SPECTRUM_SAMPLES];
+
+        for (v, lambda) in values.iter_mut().zip(wavelengths.lambda) {
+            *v = rgb_to_spectrum(color, lambda);
+        }
+
+        SampledSpectrum { values }
+    }
+
+    pub fn from_blackbody(blackbody: &Blackbody, wavelengths: &SampledWavelengths) -> Self {
+        let mut values = [0.0; SPECTRUM_SAMPLES];
+
+        for (v, lambda) in values.iter_mut().zip(wavelengths.lambda) {
+            *v = blackbody.value(lambda);
+        }
+
+        SampledSpectrum { values }
+    }
+
+    // Converts the radiance carried at the sampled wavelengths into a linear sRGB estimate
+    pub fn to_color(&self, wavelengths: &SampledWavelengths) -> Color {
+        let mut xyz = Color::new(0.0, 0.0, 0.0);
+
+        for (v, lambda) in self.values.iter().zip(wavelengths.lambda) {
+            xyz += cie_xyz(lambda) * (*v / wavelengths.pdf);
+        }
+
+        xyz /= SPECTRUM_SAMPLES as f32 * CIE_Y_INTEGRAL;
+
+        let rgb = xyz_to_srgb(xyz);
+        Color::new(
+            rgb.x * WHITE_BALANCE[0],
+            rgb.y * WHITE_BALANCE[1],
+            rgb.z * WHITE_BALANCE[2],
+        )
+    }
+}
+
+impl Add for SampledSpectrum {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut values = self.values;
+        for (v, o) in values.iter_mut().zip(other.values) {
+            *v += o;
+        }
+        SampledSpectrum { values }
+    }
+}
+
+impl AddAssign for SampledSpectrum {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Mul for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let mut values = self.values;
+        for (v, o) in values.iter_mut().zip(other.values) {
+            *v *= o;
+        }
+        SampledSpectrum { values }
+    }
+}
+
+impl Mul<f32> for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(self, other: f32) -> Self {
+        let mut values = self.values;
+        for v in values.iter_mut() {
+            *v *= other;
+        }
+        SampledSpectrum { values }
+    }
+}
+
+// Anything ray_color can accumulate light in. RGB renders use Color directly,
+// spectral renders carry one value per wavelength sampled on the camera ray.
+pub trait Radiance:
+    Copy + Add<Output = Self> + Mul<Output = Self> + Mul<f32, Output = Self>
+{
+    fn from_color(color: Color, ray: &Ray) -> Self;
+
+    // Light given off by an emitter. Spectral renders use a blackbody's own spectrum,
+    // brightened to the luminance of `color`, rather than upsampling the RGB.
+    fn from_emission(color: Color, blackbody: Option<Blackbody>, ray: &Ray) -> Self;
+
+    // A scatter's color, times the material's own per wavelength reflectance for spectral
+    // rays if it has one
+    fn from_scatter(color: Color, spectral: Option<SampledSpectrum>, ray: &Ray) -> Self;
+
+    // Called after a wavelength dependent scattering event (e.g. dispersion). Only the hero
+    // wavelength can follow the new direction, so the others are dropped and the hero is
+    // reweighted to keep the estimate unbiased. Only applies once per path, later
+    // scatters find the secondaries already gone.
+    fn terminate_secondary(self) -> Self;
+}
+
+impl Radiance for Color {
+    fn from_color(color: Color, _ray: &Ray) -> Self {
+        color
+    }
+
+    fn from_emission(color: Color, _blackbody: Option<Blackbody>, _ray: &Ray) -> Self {
+        color
+    }
+
+    fn from_scatter(color: Color, _spectral: Option<SampledSpectrum>, _ray: &Ray) -> Self {
+        color
+    }
+
+    fn terminate_secondary(self) -> Self {
+        self
+    }
+}
+
+impl Radiance for SampledSpectrum {
+    fn from_color(color: Color, ray: &Ray) -> Self {
+        let wavelengths = ray
+            .wavelengths
+            .expect("spectral rays must carry sampled wavelengths");
+        SampledSpectrum::from_rgb(color, &wavelengths)
+    }
+
+    fn from_emission(color: Color, blackbody: Option<Blackbody>, ray: &Ray) -> Self {
+        let Some(blackbody) = blackbody else {
+            return SampledSpectrum::from_color(color, ray);
+        };
+
+        let wavelengths = ray
+            .wavelengths
+            .expect("spectral rays must carry sampled wavelengths");
+        SampledSpectrum::from_blackbody(&blackbody, &wavelengths) * color.luminance()
+    }
+
+    fn from_scatter(color: Color, spectral: Option<SampledSpectrum>, ray: &Ray) -> Self {
+        let rgb = SampledSpectrum::from_color(color, ray);
+        match spectral {
+            Some(spectral) => rgb * spectral,
+            None => rgb,
+        }
+    }
+
+    fn terminate_secondary(self) -> Self {
+        let mut values = [0.0; SPECTRUM_SAMPLES];
+        values[0] = self.values[0] * SPECTRUM_SAMPLES as f32;
+        SampledSpectrum { values }
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use crate::{
+    image::Image,
+    vec3::{Color, Vec3},
+};
+
+#[derive(Clone)]
+pub enum Texture {
+    Solid(Color),
+    // Alternates between two colors in a 3D grid of cubes `scale` units wide
+    Checker { scale: f32, even: Color, odd: Color },
+    Image(Arc<Image>),
+}
+
+impl Texture {
+    pub fn solid(color: Color) -> Texture {
+        Texture::Solid(color)
+    }
+
+    pub fn checker(scale: f32, even: Color, odd: Color) -> Texture {
+        Texture::Checker { scale, even, odd }
+    }
+
+    pub fn image(image: Image) -> Texture {
+        Texture::Image(Arc::new(image))
+    }
+
+    pub fn value(&self, u: f32, v: f32, point: Vec3) -> Color {
+        match self {
+            Texture::Solid(color) => *color,
+            Texture::Checker { scale, even, odd } => {
+                let x = (point.x / scale).floor() as i32;
+                let y = (point.y / scale).floor() as i32;
+                let z = (point.z / scale).floor() as i32;
+
+                match (x + y + z) % 2 == 0 {
+                    true => *even,
+                    false => *odd,
+                }
+            }
+            Texture::Image(image) => image.sample(u, v),
+        }
+    }
+
+    // Reads the texture as a single number, for textures used as weights or masks
+    pub fn scalar(&self, u: f32, v: f32, point: Vec3) -> f32 {
+        let color = self.value(u, v, point);
+        (color.x + color.y + color.z) / 3.0
+    }
+}
@@ -213,7 +213,7 @@ impl Display for Vec3 {
 
 impl Vec3 {
     pub fn new(x: f32, y: f32, z: f32) -> Self {
-        return Vec3 { x, y, z };
+        Vec3 { x, y, z }
     }
 
     pub fn sqrt(&self) -> Self {
@@ -225,11 +225,11 @@ impl Vec3 {
     }
 
     pub fn new_fromi32(x: i32, y: i32, z: i32) -> Self {
-        return Vec3 {
+        Vec3 {
             x: x as f32,
             y: y as f32,
             z: z as f32,
-        };
+        }
     }
 
     // The square root of the sum of the squares of the vectors values.
@@ -289,7 +289,7 @@ impl Vec3 {
 
     pub fn near_zero(&self) -> bool {
         let s = 1e-8;
-        return self[0].abs() < s && self[1].abs() < s && self[2].abs() < s;
+        self[0].abs() < s && self[1].abs() < s && self[2].abs() < s
     }
 
     pub fn reflect(v: Vec3, n: Vec3) -> Self {
@@ -311,10 +311,10 @@ impl Vec3 {
     }
 
     pub fn random_in_unit_disk() -> Vec3 {
-        let mut rand_vec = Vec3::new(rand(), rand(), 0.0);
+        let mut rand_vec = Vec3::new(rand() * 2.0 - 1.0, rand() * 2.0 - 1.0, 0.0);
 
         while rand_vec.length_squared() >= 1.0 {
-            rand_vec = Vec3::new(rand(), rand(), 0.0);
+            rand_vec = Vec3::new(rand() * 2.0 - 1.0, rand() * 2.0 - 1.0, 0.0);
         }
 
         rand_vec
@@ -336,6 +336,11 @@ impl Color {
         format!("{} {} {}", self.x * 255.0, self.y * 255.0, self.z * 255.0)
     }
 
+    // Relative luminance of a linear sRGB color
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
     pub fn clamped(&self) -> Self {
         Color {
             x: clamp(self.x, 0.0, 255.0),
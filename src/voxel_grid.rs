@@ -0,0 +1,193 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+use crate::vec3::Vec3;
+
+// A dense 3D grid of values, like the density of a cloud, stored with x changing fastest,
+// then y, then z
+pub struct VoxelGrid {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub values: Vec<f32>,
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+impl VoxelGrid {
+    // Fails unless there's exactly one value for each voxel of a grid that isn't empty
+    pub fn new(
+        width: usize,
+        height: usize,
+        depth: usize,
+        values: Vec<f32>,
+    ) -> Result<VoxelGrid, Error> {
+        if width == 0 || height == 0 || depth == 0 {
+            return Err(invalid("voxel grid has no voxels"));
+        }
+
+        if width.saturating_mul(height).saturating_mul(depth) != values.len() {
+            return Err(invalid("voxel grid has the wrong number of values"));
+        }
+
+        Ok(VoxelGrid {
+            width,
+            height,
+            depth,
+            values,
+        })
+    }
+
+    // Reads the first channel of a Mitsuba .vol file
+    pub fn load(path: &str) -> Result<VoxelGrid, Error> {
+        VoxelGrid::load_channel(path, 0)
+    }
+
+    // Mitsuba .vol files are "VOL" and a version byte of 3, then little endian i32s for
+    // the encoding (1 for f32), the x, y and z resolution and the number of channels,
+    // and six f32s for a bounding box, which is ignored in favour of the medium's own
+    // placement. The f32 values follow, with the channels of each voxel together.
+    pub fn load_channel(path: &str, channel: usize) -> Result<VoxelGrid, Error> {
+        VoxelGrid::parse(&fs::read(path)?, channel)
+    }
+
+    pub fn parse(bytes: &[u8], channel: usize) -> Result<VoxelGrid, Error> {
+        if bytes.len() < 48 || &bytes[0..3] != b"VOL" || bytes[3] != 3 {
+            return Err(invalid("not a version 3 .vol file"));
+        }
+
+        let int = |offset: usize| {
+            i32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ])
+        };
+
+        if int(4) != 1 {
+            return Err(invalid("only f32 .vol files are supported"));
+        }
+
+        let dimensions = [int(8), int(12), int(16), int(20)];
+        if dimensions.iter().any(|d| *d <= 0) {
+            return Err(invalid("bad .vol header"));
+        }
+
+        let [width, height, depth, channels] = dimensions.map(|d| d as usize);
+        if channel >= channels {
+            return Err(invalid(".vol file doesn't have that many channels"));
+        }
+
+        let count = width.saturating_mul(height).saturating_mul(depth);
+        let data = &bytes[48..];
+
+        if data.len() / 4 / channels < count {
+            return Err(invalid(".vol file is missing voxel data"));
+        }
+
+        let values = data
+            .chunks_exact(4 * channels)
+            .take(count)
+            .map(|voxel| {
+                let b = &voxel[channel * 4..channel * 4 + 4];
+                f32::from_le_bytes([b[0], b[1], b[2], b[3]])
+            })
+            .collect();
+
+        VoxelGrid::new(width, height, depth, values)
+    }
+
+    pub fn max(&self) -> f32 {
+        self.values.iter().fold(0.0, |max, value| max.max(*value))
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.values[(z * self.height + y) * self.width + x]
+    }
+
+    // Trilinearly interpolates between voxel centres, for a point in the unit cube
+    // the grid covers. Points outside it get the nearest value on the edge.
+    pub fn value(&self, point: Vec3) -> f32 {
+        let axis = |p: f32, n: usize| {
+            let x = (p * n as f32 - 0.5).clamp(0.0, (n - 1) as f32);
+            let i = (x as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), x - i as f32)
+        };
+        let (x0, x1, tx) = axis(point.x, self.width);
+        let (y0, y1, ty) = axis(point.y, self.height);
+        let (z0, z1, tz) = axis(point.z, self.depth);
+
+        let lerp = |a: f32, b: f32, t: f32| a * (1.0 - t) + b * t;
+        let row = |y: usize, z: usize| lerp(self.voxel(x0, y, z), self.voxel(x1, y, z), tx);
+        let slice = |z: usize| lerp(row(y0, z), row(y1, z), ty);
+
+        lerp(slice(z0), slice(z1), tz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vol(encoding: i32, dimensions: [i32; 4], values: &[f32]) -> Vec<u8> {
+        let mut bytes = b"VOL\x03".to_vec();
+        for int in [encoding].iter().chain(&dimensions) {
+            bytes.extend_from_slice(&int.to_le_bytes());
+        }
+        for value in [0.0f32; 6].iter().chain(values) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_channel() {
+        let bytes = vol(1, [2, 1, 1, 2], &[1.0, 2.0, 3.0, 4.0]);
+
+        assert_eq!(VoxelGrid::parse(&bytes, 0).unwrap().values, vec![1.0, 3.0]);
+        assert_eq!(VoxelGrid::parse(&bytes, 1).unwrap().values, vec![2.0, 4.0]);
+    }
+
+    #[test]
+    fn interpolates_between_voxel_centres() {
+        let grid = VoxelGrid::new(2, 1, 1, vec![0.0, 1.0]).unwrap();
+
+        assert_eq!(grid.value(Vec3::new(0.5, 0.5, 0.5)), 0.5);
+        assert_eq!(grid.value(Vec3::new(0.25, 0.5, 0.5)), 0.0);
+        assert_eq!(grid.value(Vec3::new(2.0, -1.0, 0.5)), 1.0);
+        assert_eq!(grid.max(), 1.0);
+    }
+
+    #[test]
+    fn rejects_mismatched_grids() {
+        assert!(VoxelGrid::new(0, 1, 1, vec![]).is_err());
+        assert!(VoxelGrid::new(2, 2, 1, vec![0.0; 3]).is_err());
+        assert!(VoxelGrid::new(2, 2, 1, vec![0.0; 5]).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let values = [0.0; 2];
+
+        assert!(VoxelGrid::parse(b"VOL\x03", 0).is_err());
+        assert!(VoxelGrid::parse(&vol(1, [2, 1, 1, 1], &values)[..40], 0).is_err());
+
+        let mut magic = vol(1, [2, 1, 1, 1], &values);
+        magic[0] = b'X';
+        assert!(VoxelGrid::parse(&magic, 0).is_err());
+
+        let mut version = vol(1, [2, 1, 1, 1], &values);
+        version[3] = 2;
+        assert!(VoxelGrid::parse(&version, 0).is_err());
+
+        assert!(VoxelGrid::parse(&vol(2, [2, 1, 1, 1], &values), 0).is_err());
+        assert!(VoxelGrid::parse(&vol(1, [2, 1, 1, 1], &values), 1).is_err());
+        assert!(VoxelGrid::parse(&vol(1, [0, 1, 1, 1], &values), 0).is_err());
+        assert!(VoxelGrid::parse(&vol(1, [2, -1, 1, 1], &values), 0).is_err());
+        assert!(VoxelGrid::parse(&vol(1, [2, 2, 1, 1], &values), 0).is_err());
+        assert!(VoxelGrid::parse(&vol(1, [i32::MAX; 4], &values), 0).is_err());
+    }
+}